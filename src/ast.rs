@@ -1,4 +1,4 @@
-use crate::tokens::Token;
+use crate::tokens::{Token, Span};
 use crate::expressions::Expression;
 
 pub trait Node {
@@ -8,17 +8,42 @@ pub trait Node {
 
 #[derive(Debug)]
 pub enum Statement {
-    Let { token: Token, identifier: Token },
-    Return {token: Token },
-    Expression { token: Token, expression: Expression },
+    Let(LetStatement),
+    Return(ReturnStatement),
+    Expression(ExpressionStatement),
+}
+
+/// `let <identifier>[: <annotation>] = <value>;`
+#[derive(Debug)]
+pub struct LetStatement {
+    pub token: Token,
+    pub identifier: Token,
+    pub identifier_span: Span,
+    pub annotation: Option<TypeAnnotation>,
+    pub span: Span,
+}
+
+/// `return <value>;`
+#[derive(Debug)]
+pub struct ReturnStatement {
+    pub token: Token,
+    pub span: Span,
+}
+
+/// An expression on its own, as in `x;`.
+#[derive(Debug)]
+pub struct ExpressionStatement {
+    pub token: Token,
+    pub expression: Expression,
+    pub span: Span,
 }
 
 impl Node for Statement {
     fn token_literal(&self) -> String {
         match self {
-            Statement::Let { token, .. } => token.string(),
-            Statement::Return { token } => token.string(),
-            Statement::Expression { token, .. } => token.string(),
+            Statement::Let(s) => s.token.string(),
+            Statement::Return(s) => s.token.string(),
+            Statement::Expression(s) => s.token.string(),
         }
     }
 
@@ -26,23 +51,27 @@ impl Node for Statement {
         let mut buffer = String::new();
 
         match self {
-            Statement::Let { token, identifier } => {
+            Statement::Let(s) => {
 
-                buffer.push_str(&token.string());
-                buffer.push_str(" ");
-                buffer.push_str(&identifier.string());
+                buffer.push_str(&s.token.string());
+                buffer.push(' ');
+                buffer.push_str(&s.identifier.string());
+                if let Some(annotation) = &s.annotation {
+                    buffer.push_str(": ");
+                    buffer.push_str(&annotation.string());
+                }
                 buffer.push_str(" = ");
                 // TODO: expression value string
-                buffer.push_str(";");
+                buffer.push(';');
             },
-            Statement::Return { token } => {
-                buffer.push_str(&token.string());
-                buffer.push_str(" ");
+            Statement::Return(s) => {
+                buffer.push_str(&s.token.string());
+                buffer.push(' ');
                 // TODO: expression value string
-                buffer.push_str(";");
+                buffer.push(';');
             },
-            Statement::Expression { expression, .. } => {
-                buffer.push_str(&expression.string());
+            Statement::Expression(s) => {
+                buffer.push_str(&s.expression.string());
             }
         }
 
@@ -51,22 +80,44 @@ impl Node for Statement {
 }
 
 impl Statement {
-    pub fn name_token_literal(&self) -> String {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Let(s) => s.span,
+            Statement::Return(s) => s.span,
+            Statement::Expression(s) => s.span,
+        }
+    }
+
+    /// The name a `let` binds; other statements have none.
+    pub fn name_token_literal(&self) -> Option<String> {
         match self {
-            Statement::Let { identifier, .. } => identifier.string(),
-            Statement::Return { .. } => panic!("Return statement does not have identifier"),
-            Statement::Expression { .. } => panic!("Return statement does not have identifier")
+            Statement::Let(s) => Some(s.identifier.string()),
+            Statement::Return(_) | Statement::Expression(_) => None,
         }
     }
 }
 
+/// A type written after a name, as in `let x: int = 5;`. Type names are
+/// plain identifiers and are not checked.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TypeAnnotation {
+    pub token: Token,
+    pub span: Span,
+}
+
+impl TypeAnnotation {
+    pub fn string(&self) -> String {
+        self.token.string()
+    }
+}
+
 pub struct Program {
     pub statements: Vec<Statement>,
 }
 
 impl Node for Program {
     fn token_literal(&self) -> String {
-        if self.statements.len() > 0 {
+        if !self.statements.is_empty() {
             self.statements[0].token_literal()
         } else {
             String::from("")
@@ -83,6 +134,130 @@ impl Node for Program {
     }
 }
 
+/// Read-only traversal of the AST.
+///
+/// Every method defaults to walking into the children of its node, so a pass
+/// only overrides the nodes it cares about.
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program)
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement)
+    }
+
+    fn visit_let_statement(&mut self, statement: &LetStatement) {
+        walk_let_statement(self, statement)
+    }
+
+    fn visit_type_annotation(&mut self, _annotation: &TypeAnnotation) {}
+
+    fn visit_return_statement(&mut self, _statement: &ReturnStatement) {}
+
+    fn visit_expression_statement(&mut self, statement: &ExpressionStatement) {
+        self.visit_expression(&statement.expression)
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression)
+    }
+
+    fn visit_identifier(&mut self, _token: &Token, _span: Span) {}
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for statement in &program.statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::Let(s) => visitor.visit_let_statement(s),
+        Statement::Return(s) => visitor.visit_return_statement(s),
+        Statement::Expression(s) => visitor.visit_expression_statement(s),
+    }
+}
+
+pub fn walk_let_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &LetStatement) {
+    if let Some(annotation) = &statement.annotation {
+        visitor.visit_type_annotation(annotation);
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::Identifier { token, span } => visitor.visit_identifier(token, *span),
+    }
+}
+
+/// Owning transformation of the AST, rebuilding each node from its folded
+/// children. As with `Visitor`, only the interesting nodes need overriding.
+pub trait Fold {
+    fn fold_program(&mut self, program: Program) -> Program {
+        fold_program(self, program)
+    }
+
+    fn fold_statement(&mut self, statement: Statement) -> Statement {
+        fold_statement(self, statement)
+    }
+
+    fn fold_let_statement(&mut self, statement: LetStatement) -> Statement {
+        fold_let_statement(self, statement)
+    }
+
+    fn fold_type_annotation(&mut self, annotation: TypeAnnotation) -> TypeAnnotation {
+        annotation
+    }
+
+    fn fold_return_statement(&mut self, statement: ReturnStatement) -> Statement {
+        Statement::Return(statement)
+    }
+
+    fn fold_expression_statement(&mut self, statement: ExpressionStatement) -> Statement {
+        let expression = self.fold_expression(statement.expression);
+        Statement::Expression(ExpressionStatement { expression, ..statement })
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        fold_expression(self, expression)
+    }
+
+    fn fold_identifier(&mut self, token: Token, span: Span) -> Expression {
+        Expression::Identifier { token, span }
+    }
+}
+
+pub fn fold_program<F: Fold + ?Sized>(folder: &mut F, program: Program) -> Program {
+    Program {
+        statements: program
+            .statements
+            .into_iter()
+            .map(|statement| folder.fold_statement(statement))
+            .collect(),
+    }
+}
+
+pub fn fold_statement<F: Fold + ?Sized>(folder: &mut F, statement: Statement) -> Statement {
+    match statement {
+        Statement::Let(s) => folder.fold_let_statement(s),
+        Statement::Return(s) => folder.fold_return_statement(s),
+        Statement::Expression(s) => folder.fold_expression_statement(s),
+    }
+}
+
+pub fn fold_let_statement<F: Fold + ?Sized>(folder: &mut F, statement: LetStatement) -> Statement {
+    let annotation = statement.annotation.map(|annotation| folder.fold_type_annotation(annotation));
+    Statement::Let(LetStatement { annotation, ..statement })
+}
+
+pub fn fold_expression<F: Fold + ?Sized>(folder: &mut F, expression: Expression) -> Expression {
+    match expression {
+        Expression::Identifier { token, span } => folder.fold_identifier(token, span),
+    }
+}
+
 #[cfg(test)]
 mod test{
     use super::*;
@@ -91,16 +266,107 @@ mod test{
     fn string() {
         let program = Program {
             statements: vec![
-                Statement::Let {
+                Statement::Let(LetStatement {
                     token: Token::Let,
                     identifier: Token::Ident("myVar".to_string()),
-                }
+                    identifier_span: Span::new(4, 9),
+                    annotation: None,
+                    span: Span::new(0, 13),
+                })
             ]
         };
 
         let expected = String::from("let myVar = ;");
         let string = program.string();
 
-        assert_eq!(expected, string, "Wrong program. expected {} got {}", string, expected)
+        assert_eq!(expected, string, "Wrong program. expected {} got {}", string, expected);
+
+        let annotated = Statement::Let(LetStatement {
+            token: Token::Let,
+            identifier: Token::Ident("x".to_string()),
+            identifier_span: Span::new(4, 5),
+            annotation: Some(TypeAnnotation { token: Token::Ident("int".to_string()), span: Span::new(7, 10) }),
+            span: Span::new(0, 15),
+        });
+        assert_eq!(annotated.string(), "let x: int = ;");
+    }
+
+    struct Identifiers {
+        names: Vec<String>,
+    }
+
+    impl Visitor for Identifiers {
+        fn visit_let_statement(&mut self, statement: &LetStatement) {
+            self.names.push(format!("let {}", statement.identifier.string()));
+            walk_let_statement(self, statement);
+        }
+
+        fn visit_type_annotation(&mut self, annotation: &TypeAnnotation) {
+            self.names.push(format!("type {}", annotation.string()));
+        }
+
+        fn visit_identifier(&mut self, token: &Token, _span: Span) {
+            self.names.push(token.string());
+        }
+    }
+
+    struct Rename;
+
+    impl Fold for Rename {
+        fn fold_identifier(&mut self, token: Token, span: Span) -> Expression {
+            let renamed = format!("{}_renamed", token.string());
+            Expression::Identifier { token: Token::Ident(renamed), span }
+        }
+
+        fn fold_type_annotation(&mut self, annotation: TypeAnnotation) -> TypeAnnotation {
+            TypeAnnotation { token: Token::Ident(format!("{}_t", annotation.string())), ..annotation }
+        }
+    }
+
+    fn program() -> Program {
+        Program {
+            statements: vec![
+                Statement::Let(LetStatement {
+                    token: Token::Let,
+                    identifier: Token::Ident("myVar".to_string()),
+                    identifier_span: Span::new(4, 9),
+                    annotation: Some(TypeAnnotation { token: Token::Ident("int".to_string()), span: Span::new(11, 14) }),
+                    span: Span::new(0, 19),
+                }),
+                Statement::Expression(ExpressionStatement {
+                    token: Token::Ident("myVar".to_string()),
+                    expression: Expression::Identifier {
+                        token: Token::Ident("myVar".to_string()),
+                        span: Span::new(15, 20),
+                    },
+                    span: Span::new(15, 21),
+                }),
+                Statement::Return(ReturnStatement { token: Token::Return, span: Span::new(22, 31) }),
+                Statement::Expression(ExpressionStatement {
+                    token: Token::Ident("other".to_string()),
+                    expression: Expression::Identifier {
+                        token: Token::Ident("other".to_string()),
+                        span: Span::new(32, 37),
+                    },
+                    span: Span::new(32, 38),
+                }),
+            ]
+        }
+    }
+
+    #[test]
+    fn visitor() {
+        let mut visitor = Identifiers { names: vec![] };
+        visitor.visit_program(&program());
+
+        assert_eq!(visitor.names, vec!["let myVar", "type int", "myVar", "other"]);
+    }
+
+    #[test]
+    fn fold() {
+        let folded = Rename.fold_program(program());
+
+        let expected = String::from("let myVar: int_t = ;\nmyVar_renamed\nreturn ;\nother_renamed");
+        assert_eq!(folded.string(), expected);
     }
 }
@@ -0,0 +1,463 @@
+use crate::tokens::{Token, Span, Trivia};
+use crate::lexer::Lexer;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum NodeKind {
+    Program,
+    LetStatement,
+    ReturnStatement,
+    ExpressionStatement,
+    Identifier,
+    // `: type` after a `let` name
+    TypeAnnotation,
+    // tokens the parser does not parse into an expression yet
+    Expression,
+    // tokens the parser rejects, up to the end of the statement
+    Error,
+}
+
+/// A token with its exact spelling and the trivia in front of it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SyntaxToken {
+    pub leading: Vec<Trivia>,
+    pub token: Token,
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Element {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// Lossless concrete syntax tree: concatenating the trivia and text of every
+/// token gives back the source byte for byte.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub children: Vec<Element>,
+}
+
+impl SyntaxNode {
+    fn new(kind: NodeKind) -> Self {
+        SyntaxNode { kind, children: vec![] }
+    }
+
+    pub fn text(&self) -> String {
+        let mut buffer = String::new();
+
+        for token in self.tokens() {
+            for trivia in &token.leading {
+                buffer.push_str(&trivia.text);
+            }
+            buffer.push_str(&token.text);
+        }
+
+        buffer
+    }
+
+    /// Every token under this node, in source order.
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = vec![];
+
+        for child in &self.children {
+            match child {
+                Element::Token(token) => tokens.push(token),
+                Element::Node(node) => tokens.extend(node.tokens()),
+            }
+        }
+
+        tokens
+    }
+
+    /// Span from the first to the last token, leaving out leading trivia.
+    pub fn span(&self) -> Span {
+        let tokens = self.tokens();
+        match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => first.span.to(last.span),
+            _ => Span::default(),
+        }
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            Element::Node(node) => Some(node),
+            Element::Token(_) => None,
+        })
+    }
+
+    pub fn child_tokens(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.children.iter().filter_map(|child| match child {
+            Element::Token(token) => Some(token),
+            Element::Node(_) => None,
+        })
+    }
+}
+
+/// Builds the concrete syntax tree of `source`. It never fails: input the
+/// parser would reject ends up in `Error` nodes.
+pub fn parse(source: &str) -> SyntaxNode {
+    let mut l = Lexer::new(source);
+    let mut tokens = vec![];
+
+    loop {
+        let (leading, token, span) = l.next_token_with_trivia();
+        let text = l.slice(span);
+        let eof = token == Token::Eof;
+
+        tokens.push(SyntaxToken { leading, token, text, span });
+        if eof {
+            break;
+        }
+    }
+
+    let mut builder = Builder { tokens: tokens.into_iter(), peek: None };
+    builder.program()
+}
+
+struct Builder {
+    tokens: std::vec::IntoIter<SyntaxToken>,
+    peek: Option<SyntaxToken>,
+}
+
+impl Builder {
+    fn peek(&mut self) -> &Token {
+        if self.peek.is_none() {
+            self.peek = self.tokens.next();
+        }
+        // the token stream ends with Eof, which is only bumped by `program`
+        &self.peek.as_ref().expect("token stream ended before Eof").token
+    }
+
+    fn bump(&mut self, node: &mut SyntaxNode) {
+        self.peek();
+        if let Some(token) = self.peek.take() {
+            node.children.push(Element::Token(token));
+        }
+    }
+
+    /// Moves every token up to the `;` that ends the statement into `node`,
+    /// stepping over any `;` nested in `{}` or `()`.
+    fn bump_to_statement_end(&mut self, node: &mut SyntaxNode) {
+        let mut depth = 0usize;
+
+        loop {
+            match self.peek() {
+                Token::Eof => break,
+                Token::SemiColon if depth == 0 => break,
+                Token::LBrace | Token::LParen => depth += 1,
+                Token::RBrace | Token::RParen => depth = depth.saturating_sub(1),
+                _ => {},
+            }
+            self.bump(node);
+        }
+    }
+
+    fn program(&mut self) -> SyntaxNode {
+        let mut program = SyntaxNode::new(NodeKind::Program);
+
+        while *self.peek() != Token::Eof {
+            let statement = self.statement();
+            program.children.push(Element::Node(statement));
+        }
+        self.bump(&mut program);
+
+        program
+    }
+
+    fn statement(&mut self) -> SyntaxNode {
+        match self.peek() {
+            Token::Let => self.let_statement(),
+            Token::Return => self.return_statement(),
+            _ => self.expression_statement(),
+        }
+    }
+
+    fn let_statement(&mut self) -> SyntaxNode {
+        let mut node = SyntaxNode::new(NodeKind::LetStatement);
+        self.bump(&mut node);
+
+        if !matches!(self.peek(), Token::Ident(_)) {
+            return self.error(node);
+        }
+        self.bump(&mut node);
+
+        if *self.peek() == Token::Colon {
+            let mut annotation = SyntaxNode::new(NodeKind::TypeAnnotation);
+            self.bump(&mut annotation);
+
+            if !matches!(self.peek(), Token::Ident(_)) {
+                node.children.push(Element::Node(annotation));
+                return self.error(node);
+            }
+            self.bump(&mut annotation);
+            node.children.push(Element::Node(annotation));
+        }
+
+        if *self.peek() != Token::Assign {
+            return self.error(node);
+        }
+        self.bump(&mut node);
+
+        self.value(&mut node);
+        self.semicolon(&mut node);
+        node
+    }
+
+    fn return_statement(&mut self) -> SyntaxNode {
+        let mut node = SyntaxNode::new(NodeKind::ReturnStatement);
+        self.bump(&mut node);
+
+        self.value(&mut node);
+        self.semicolon(&mut node);
+        node
+    }
+
+    fn expression_statement(&mut self) -> SyntaxNode {
+        let mut node = SyntaxNode::new(NodeKind::ExpressionStatement);
+
+        // identifiers are the only expressions the parser knows
+        if !matches!(self.peek(), Token::Ident(_)) {
+            return self.error(node);
+        }
+
+        let mut identifier = SyntaxNode::new(NodeKind::Identifier);
+        self.bump(&mut identifier);
+        node.children.push(Element::Node(identifier));
+
+        self.semicolon(&mut node);
+        node
+    }
+
+    /// The tokens up to the end of a `let` or `return` statement.
+    fn value(&mut self, node: &mut SyntaxNode) {
+        let mut value = SyntaxNode::new(NodeKind::Expression);
+        self.bump_to_statement_end(&mut value);
+
+        if let [Element::Token(SyntaxToken { token: Token::Ident(_), .. })] = value.children.as_slice() {
+            value.kind = NodeKind::Identifier;
+        }
+        if !value.children.is_empty() {
+            node.children.push(Element::Node(value));
+        }
+    }
+
+    fn semicolon(&mut self, node: &mut SyntaxNode) {
+        if *self.peek() == Token::SemiColon {
+            self.bump(node);
+        }
+    }
+
+    /// Turns a partial statement into an error node running to the next `;`.
+    fn error(&mut self, mut node: SyntaxNode) -> SyntaxNode {
+        node.kind = NodeKind::Error;
+        self.bump_to_statement_end(&mut node);
+        self.semicolon(&mut node);
+        node
+    }
+}
+
+/// Typed view of a `Program` node.
+#[derive(Debug, Clone, Copy)]
+pub struct Program<'a>(&'a SyntaxNode);
+
+impl<'a> Program<'a> {
+    pub fn cast(node: &'a SyntaxNode) -> Option<Self> {
+        (node.kind == NodeKind::Program).then_some(Program(node))
+    }
+
+    pub fn syntax(&self) -> &'a SyntaxNode {
+        self.0
+    }
+
+    /// Well-formed statements; error nodes are skipped.
+    pub fn statements(&self) -> impl Iterator<Item = Statement<'a>> {
+        self.0.child_nodes().filter_map(Statement::cast)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Statement<'a> {
+    Let(LetStatement<'a>),
+    Return(ReturnStatement<'a>),
+    Expression(ExpressionStatement<'a>),
+}
+
+impl<'a> Statement<'a> {
+    pub fn cast(node: &'a SyntaxNode) -> Option<Self> {
+        match node.kind {
+            NodeKind::LetStatement => Some(Statement::Let(LetStatement(node))),
+            NodeKind::ReturnStatement => Some(Statement::Return(ReturnStatement(node))),
+            NodeKind::ExpressionStatement => Some(Statement::Expression(ExpressionStatement(node))),
+            _ => None,
+        }
+    }
+
+    pub fn syntax(&self) -> &'a SyntaxNode {
+        match self {
+            Statement::Let(s) => s.0,
+            Statement::Return(s) => s.0,
+            Statement::Expression(s) => s.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LetStatement<'a>(&'a SyntaxNode);
+
+impl<'a> LetStatement<'a> {
+    pub fn name(&self) -> &'a SyntaxToken {
+        self.0
+            .child_tokens()
+            .find(|t| matches!(t.token, Token::Ident(_)))
+            .expect("let statement without a name is an error node")
+    }
+
+    /// The type name after `:`, if there is one.
+    pub fn annotation(&self) -> Option<&'a SyntaxToken> {
+        self.0
+            .child_nodes()
+            .find(|n| n.kind == NodeKind::TypeAnnotation)
+            .and_then(|n| n.child_tokens().find(|t| matches!(t.token, Token::Ident(_))))
+    }
+
+    pub fn value(&self) -> Option<&'a SyntaxNode> {
+        self.0.child_nodes().find(|n| n.kind != NodeKind::TypeAnnotation)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReturnStatement<'a>(&'a SyntaxNode);
+
+impl<'a> ReturnStatement<'a> {
+    pub fn value(&self) -> Option<&'a SyntaxNode> {
+        self.0.child_nodes().next()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExpressionStatement<'a>(&'a SyntaxNode);
+
+impl<'a> ExpressionStatement<'a> {
+    pub fn expression(&self) -> Identifier<'a> {
+        self.0
+            .child_nodes()
+            .find_map(Identifier::cast)
+            .expect("expression statement without an identifier is an error node")
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Identifier<'a>(&'a SyntaxNode);
+
+impl<'a> Identifier<'a> {
+    pub fn cast(node: &'a SyntaxNode) -> Option<Self> {
+        (node.kind == NodeKind::Identifier).then_some(Identifier(node))
+    }
+
+    pub fn name(&self) -> &'a str {
+        &self.0.tokens()[0].text
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lossless() {
+        let inputs = [
+            "",
+            "   \n\t",
+            "let x = 5;",
+            "let  five =  5 ;\r\n\r\nlet add = fn(x, y) {\n\tx + y;\n};\n",
+            "return add(five, ten)",
+            "foobar;\nfoo bar\n",
+            "let = 10; let 838383; ;; @ ünïcode\n",
+            "let x : int = 5; let y: = 1; // done\n",
+            "if (5 < 10) { return true; } else { return false; }",
+        ];
+
+        for input in inputs {
+            let tree = parse(input);
+            assert_eq!(tree.text(), input, "Tree does not rebuild the input: {:#?}", tree);
+        }
+    }
+
+    #[test]
+    fn statements() {
+        let tree = parse("let x = 5;\nreturn y;\nfoobar;\nlet = 10;\nlet z;");
+        let kinds: Vec<NodeKind> = tree.child_nodes().map(|n| n.kind).collect();
+
+        assert_eq!(kinds, vec![
+            NodeKind::LetStatement,
+            NodeKind::ReturnStatement,
+            NodeKind::ExpressionStatement,
+            NodeKind::Error,
+            NodeKind::Error,
+        ]);
+
+        let texts: Vec<String> = tree.child_nodes().map(|n| n.text()).collect();
+        assert_eq!(texts, vec!["let x = 5;", "\nreturn y;", "\nfoobar;", "\nlet = 10;", "\nlet z;"]);
+        assert_eq!(tree.child_nodes().nth(1).unwrap().span(), Span::new(11, 20));
+
+        let tree = parse("let add = fn(x, y) { x + y; };\nadd;");
+        let kinds: Vec<NodeKind> = tree.child_nodes().map(|n| n.kind).collect();
+        assert_eq!(kinds, vec![NodeKind::LetStatement, NodeKind::ExpressionStatement]);
+        assert_eq!(tree.child_nodes().next().unwrap().span(), Span::new(0, 30));
+    }
+
+    #[test]
+    fn typed_view() {
+        let tree = parse("let  myVar = anotherVar ;\nreturn 5 * 2;\nfoobar");
+        let program = Program::cast(&tree).unwrap();
+        let statements: Vec<Statement> = program.statements().collect();
+
+        assert_eq!(statements.len(), 3);
+
+        match statements[0] {
+            Statement::Let(s) => {
+                assert_eq!(s.name().text, "myVar");
+                assert_eq!(s.name().leading[0].text, "  ");
+
+                assert_eq!(s.annotation(), None);
+
+                let value = s.value().unwrap();
+                assert_eq!(Identifier::cast(value).unwrap().name(), "anotherVar");
+            },
+            s => panic!("Statement not let. Got {:?}", s),
+        }
+
+        match statements[1] {
+            Statement::Return(s) => {
+                let value = s.value().unwrap();
+                assert_eq!(value.kind, NodeKind::Expression);
+                assert_eq!(value.text(), " 5 * 2");
+            },
+            s => panic!("Statement not return. Got {:?}", s),
+        }
+
+        match statements[2] {
+            Statement::Expression(s) => assert_eq!(s.expression().name(), "foobar"),
+            s => panic!("Statement not expression. Got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn annotation() {
+        let tree = parse("let x: int = y;\nlet z: = 1;");
+        let kinds: Vec<NodeKind> = tree.child_nodes().map(|n| n.kind).collect();
+        assert_eq!(kinds, vec![NodeKind::LetStatement, NodeKind::Error]);
+
+        let statement = Program::cast(&tree).unwrap().statements().next();
+        match statement {
+            Some(Statement::Let(s)) => {
+                assert_eq!(s.annotation().unwrap().text, "int");
+                assert_eq!(s.annotation().unwrap().span, Span::new(7, 10));
+                assert_eq!(Identifier::cast(s.value().unwrap()).unwrap().name(), "y");
+            },
+            s => panic!("Statement not let. Got {:?}", s),
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use std::io::{stderr, IsTerminal, Write};
+use crate::tokens::Span;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A problem found in the source, shared by the lexer and the parser.
+///
+/// The primary `span` is underlined with `^`, secondary labels with `-`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    // rule that produced it, shown as `warning[code]`
+    pub code: Option<String>,
+    pub message: String,
+    pub span: Span,
+    pub label: Option<String>,
+    pub secondary: Vec<Label>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>, span: Span) -> Self {
+        Diagnostic {
+            severity,
+            code: None,
+            message: message.into(),
+            span,
+            label: None,
+            secondary: vec![],
+            help: vec![],
+        }
+    }
+
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Self::new(Severity::Error, message, span)
+    }
+
+    pub fn warning(message: impl Into<String>, span: Span) -> Self {
+        Self::new(Severity::Warning, message, span)
+    }
+
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    pub fn with_label(mut self, message: impl Into<String>) -> Self {
+        self.label = Some(message.into());
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.secondary.push(Label { span, message: message.into() });
+        self
+    }
+
+    pub fn with_help(mut self, message: impl Into<String>) -> Self {
+        self.help.push(message.into());
+        self
+    }
+
+    /// Renders the diagnostic against `source`, which `file` names in the header.
+    pub fn render(&self, file: &str, source: &str, color: bool) -> String {
+        let paint = |code: &str, text: &str| {
+            if color {
+                format!("{}{}{}", code, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+
+        let lines = SourceLines::new(source);
+        let (line, col) = lines.location(self.span.start);
+
+        let mut underlines = vec![];
+        let primary_message = self.label.clone().unwrap_or_default();
+        underlines.push(lines.underline(self.span, '^', self.severity.color(), primary_message));
+        for label in &self.secondary {
+            underlines.push(lines.underline(label.span, '-', BLUE, label.message.clone()));
+        }
+
+        underlines.sort_by_key(|u| (u.line, u.start));
+
+        let mut shown: Vec<usize> = underlines.iter().map(|u| u.line).collect();
+        shown.sort();
+        shown.dedup();
+
+        let width = (shown.last().copied().unwrap_or(line) + 1).to_string().len();
+        let pad = " ".repeat(width);
+        let bar = paint(BLUE, "|");
+
+        let mut out = String::new();
+        let mut header = self.severity.name().to_string();
+        if let Some(code) = &self.code {
+            header.push_str(&format!("[{}]", code));
+        }
+        out.push_str(&paint(self.severity.color(), &header));
+        out.push_str(&paint(BOLD, &format!(": {}", self.message)));
+        out.push('\n');
+        out.push_str(&format!("{}{} {}:{}:{}\n", pad, paint(BLUE, "-->"), file, line + 1, col + 1));
+        out.push_str(&format!("{} {}\n", pad, bar));
+
+        let mut previous: Option<usize> = None;
+        for number in shown {
+            if let Some(previous) = previous {
+                if number > previous + 1 {
+                    out.push_str(&paint(BLUE, "..."));
+                    out.push('\n');
+                }
+            }
+            previous = Some(number);
+
+            let text = lines.text(number);
+            let gutter = paint(BLUE, &format!("{:>width$}", number + 1, width = width));
+            out.push_str(format!("{} {} {}", gutter, bar, text).trim_end());
+            out.push('\n');
+
+            for underline in underlines.iter().filter(|u| u.line == number) {
+                // keep tabs so the markers line up with the source line above
+                let indent: String = text
+                    .chars()
+                    .take(underline.start)
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+                let markers = underline.marker.to_string().repeat(underline.end - underline.start);
+                let mut row = format!("{} {} {}{}", pad, bar, indent, paint(underline.color, &markers));
+                if !underline.message.is_empty() {
+                    row.push(' ');
+                    row.push_str(&paint(underline.color, &underline.message));
+                }
+                out.push_str(&row);
+                out.push('\n');
+            }
+        }
+
+        if !self.help.is_empty() {
+            out.push_str(&format!("{} {}\n", pad, bar));
+        }
+        for help in &self.help {
+            out.push_str(&format!("{} {} {}: {}\n", pad, paint(BLUE, "="), paint(BOLD, "help"), help));
+        }
+
+        out
+    }
+}
+
+struct Underline {
+    line: usize,
+    start: usize,
+    end: usize,
+    marker: char,
+    color: &'static str,
+    message: String,
+}
+
+/// The character offset at which each line of a source starts, to turn
+/// span offsets into lines and columns.
+pub(crate) struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(source: &str) -> Self {
+        let mut starts = vec![0];
+        for (i, c) in source.chars().enumerate() {
+            if c == '\n' {
+                starts.push(i + 1);
+            }
+        }
+
+        LineIndex { starts }
+    }
+
+    /// Zero based line and column of a character offset.
+    pub(crate) fn location(&self, offset: usize) -> (usize, usize) {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        (line, offset - self.starts[line])
+    }
+
+    /// Offset of the first character of a zero based line.
+    pub(crate) fn start(&self, line: usize) -> Option<usize> {
+        self.starts.get(line).copied()
+    }
+}
+
+struct SourceLines {
+    lines: Vec<String>,
+    index: LineIndex,
+}
+
+impl SourceLines {
+    fn new(source: &str) -> Self {
+        let lines = source.split('\n').map(|line| line.trim_end_matches('\r').to_string()).collect();
+
+        SourceLines { lines, index: LineIndex::new(source) }
+    }
+
+    fn location(&self, offset: usize) -> (usize, usize) {
+        self.index.location(offset)
+    }
+
+    fn text(&self, line: usize) -> &str {
+        &self.lines[line]
+    }
+
+    /// Spans running past the end of their first line are cut at the line end.
+    fn underline(&self, span: Span, marker: char, color: &'static str, message: String) -> Underline {
+        let (line, start) = self.location(span.start);
+        let length = self.lines[line].chars().count();
+        let end = if span.end > span.start {
+            (span.end - self.index.starts[line]).min(length).max(start + 1)
+        } else {
+            start + 1
+        };
+
+        Underline { line, start, end, marker, color, message }
+    }
+}
+
+/// Prints diagnostics to stderr, using color only when stderr is a terminal.
+pub fn emit(diagnostics: &[Diagnostic], file: &str, source: &str) {
+    let color = stderr().is_terminal();
+    let mut err = stderr().lock();
+
+    for diagnostic in diagnostics {
+        let _ = writeln!(err, "{}", diagnostic.render(file, source, color));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_primary_label() {
+        let source = "let x 5;";
+        let diagnostic = Diagnostic::error("expected `=`, found integer `5`", Span::new(6, 7))
+            .with_label("expected `=`");
+
+        let expected = "\
+error: expected `=`, found integer `5`
+ --> test.mk:1:7
+  |
+1 | let x 5;
+  |       ^ expected `=`
+";
+
+        assert_eq!(diagnostic.render("test.mk", source, false), expected);
+    }
+
+    #[test]
+    fn render_secondary_labels_and_help() {
+        let source = "let x = 1;\nlet y = 2;\n\n\nlet x = 3;";
+        let diagnostic = Diagnostic::warning("`x` is declared twice", Span::new(28, 29))
+            .with_label("redeclared here")
+            .with_secondary(Span::new(4, 5), "first declared here")
+            .with_help("rename one of the bindings");
+
+        let expected = "\
+warning: `x` is declared twice
+ --> test.mk:5:5
+  |
+1 | let x = 1;
+  |     - first declared here
+...
+5 | let x = 3;
+  |     ^ redeclared here
+  |
+  = help: rename one of the bindings
+";
+
+        assert_eq!(diagnostic.render("test.mk", source, false), expected);
+    }
+
+    #[test]
+    fn render_end_of_input() {
+        let source = "let x =";
+        let diagnostic = Diagnostic::error("unexpected end of input", Span::new(7, 7));
+
+        let expected = "\
+error: unexpected end of input
+ --> test.mk:1:8
+  |
+1 | let x =
+  |        ^
+";
+
+        assert_eq!(diagnostic.render("test.mk", source, false), expected);
+    }
+
+    #[test]
+    fn render_with_color() {
+        let source = "@";
+        let diagnostic = Diagnostic::error("illegal character `@`", Span::new(0, 1));
+        let rendered = diagnostic.render("test.mk", source, true);
+
+        assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m"), "Missing color codes, got {:?}", rendered);
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m"), "Missing colored caret, got {:?}", rendered);
+    }
+}
@@ -0,0 +1,224 @@
+use serde_json::{json, Value};
+use crate::tokens::{Token, Span, TriviaKind};
+use crate::lexer::Lexer;
+use crate::ast::{Program, Statement};
+use crate::expressions::Expression;
+
+fn span(span: Span) -> Value {
+    json!({ "start": span.start, "end": span.end })
+}
+
+/// JSON form of a program. Every node has a `kind` and, where the parser
+/// records one, a `span` of character offsets into the source.
+pub fn to_json(program: &Program) -> Value {
+    let statements: Vec<Value> = program.statements.iter().map(statement_json).collect();
+    let program_span = match (program.statements.first(), program.statements.last()) {
+        (Some(first), Some(last)) => first.span().to(last.span()),
+        _ => Span::default(),
+    };
+
+    json!({
+        "kind": "Program",
+        "span": span(program_span),
+        "statements": statements,
+    })
+}
+
+fn statement_json(statement: &Statement) -> Value {
+    match statement {
+        Statement::Let(s) => {
+            let mut value = json!({
+                "kind": "LetStatement",
+                "span": span(s.span),
+                "name": {
+                    "kind": "Identifier",
+                    "value": s.identifier.string(),
+                    "span": span(s.identifier_span),
+                },
+            });
+            if let Some(annotation) = &s.annotation {
+                value["annotation"] = json!({
+                    "kind": "TypeAnnotation",
+                    "value": annotation.string(),
+                    "span": span(annotation.span),
+                });
+            }
+            value
+        },
+        Statement::Return(s) => json!({
+            "kind": "ReturnStatement",
+            "span": span(s.span),
+        }),
+        Statement::Expression(s) => json!({
+            "kind": "ExpressionStatement",
+            "span": span(s.span),
+            "expression": expression_json(&s.expression),
+        }),
+    }
+}
+
+fn expression_json(expression: &Expression) -> Value {
+    match expression {
+        Expression::Identifier { token, span: identifier_span } => json!({
+            "kind": "Identifier",
+            "value": token.string(),
+            "span": span(*identifier_span),
+        }),
+    }
+}
+
+/// Compact S-expression form of a program, without spans, e.g.
+/// `(program (let x) (return) (ident x))`.
+pub fn to_sexp(program: &Program) -> String {
+    let mut buffer = String::from("(program");
+
+    for statement in &program.statements {
+        buffer.push(' ');
+        buffer.push_str(&statement_sexp(statement));
+    }
+
+    buffer.push(')');
+    buffer
+}
+
+fn statement_sexp(statement: &Statement) -> String {
+    match statement {
+        Statement::Let(s) => match &s.annotation {
+            Some(annotation) => format!("(let {} (type {}))", s.identifier.string(), annotation.string()),
+            None => format!("(let {})", s.identifier.string()),
+        },
+        Statement::Return(_) => String::from("(return)"),
+        Statement::Expression(s) => expression_sexp(&s.expression),
+    }
+}
+
+fn expression_sexp(expression: &Expression) -> String {
+    match expression {
+        Expression::Identifier { token, .. } => format!("(ident {})", token.string()),
+    }
+}
+
+/// The token stream of `source`, one JSON object per token with its kind
+/// (from `Token::name`), exact lexeme and span. With `include_trivia` the
+/// whitespace and `//` comments between tokens are included as well.
+pub fn tokens_json(source: &str, include_trivia: bool) -> Vec<Value> {
+    let mut l = Lexer::new(source);
+    let mut tokens = vec![];
+
+    loop {
+        let (trivia, token, token_span) = l.next_token_with_trivia();
+
+        if include_trivia {
+            for t in trivia {
+                let kind = match t.kind {
+                    TriviaKind::Whitespace => "whitespace",
+                    TriviaKind::Comment => "comment",
+                };
+                tokens.push(json!({ "kind": kind, "lexeme": t.text, "span": span(t.span) }));
+            }
+        }
+
+        if token == Token::Eof {
+            break;
+        }
+
+        tokens.push(json!({
+            "kind": Token::name(&token),
+            "lexeme": l.slice(token_span),
+            "span": span(token_span),
+        }));
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn parse(input: &str) -> Program {
+        let mut p = Parser::new(Lexer::new(input));
+        let program = p.parse_program();
+        assert!(p.errors().is_empty(), "Parser errors {:?}", p.errors());
+        program
+    }
+
+    #[test]
+    fn json() {
+        let program = parse("let x = 5;\nreturn x;\nfoobar");
+
+        let expected = json!({
+            "kind": "Program",
+            "span": { "start": 0, "end": 27 },
+            "statements": [
+                {
+                    "kind": "LetStatement",
+                    "span": { "start": 0, "end": 10 },
+                    "name": { "kind": "Identifier", "value": "x", "span": { "start": 4, "end": 5 } },
+                },
+                {
+                    "kind": "ReturnStatement",
+                    "span": { "start": 11, "end": 20 },
+                },
+                {
+                    "kind": "ExpressionStatement",
+                    "span": { "start": 21, "end": 27 },
+                    "expression": { "kind": "Identifier", "value": "foobar", "span": { "start": 21, "end": 27 } },
+                },
+            ],
+        });
+
+        assert_eq!(to_json(&program), expected);
+
+        let annotated = to_json(&parse("let x: int = 5;"));
+        assert_eq!(
+            annotated["statements"][0]["annotation"],
+            json!({ "kind": "TypeAnnotation", "value": "int", "span": { "start": 7, "end": 10 } }),
+        );
+    }
+
+    #[test]
+    fn sexp() {
+        let tests = [
+            ("", "(program)"),
+            ("let x = 5;", "(program (let x))"),
+            ("let x = 5;\nreturn 10;\nfoobar;", "(program (let x) (return) (ident foobar))"),
+            ("foo bar", "(program (ident foo) (ident bar))"),
+            ("let x: int = 5;", "(program (let x (type int)))"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(to_sexp(&parse(input)), expected, "Wrong S-expression for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn tokens() {
+        let tokens = tokens_json("let x = 5;\n@", false);
+
+        let expected = vec![
+            json!({ "kind": "let", "lexeme": "let", "span": { "start": 0, "end": 3 } }),
+            json!({ "kind": "identifier", "lexeme": "x", "span": { "start": 4, "end": 5 } }),
+            json!({ "kind": "=", "lexeme": "=", "span": { "start": 6, "end": 7 } }),
+            json!({ "kind": "integer", "lexeme": "5", "span": { "start": 8, "end": 9 } }),
+            json!({ "kind": ";", "lexeme": ";", "span": { "start": 9, "end": 10 } }),
+            json!({ "kind": "illegal", "lexeme": "@", "span": { "start": 11, "end": 12 } }),
+        ];
+
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn tokens_with_trivia() {
+        let tokens = tokens_json(" x\t\n", true);
+
+        let expected = vec![
+            json!({ "kind": "whitespace", "lexeme": " ", "span": { "start": 0, "end": 1 } }),
+            json!({ "kind": "identifier", "lexeme": "x", "span": { "start": 1, "end": 2 } }),
+            json!({ "kind": "whitespace", "lexeme": "\t\n", "span": { "start": 2, "end": 4 } }),
+        ];
+
+        assert_eq!(tokens, expected);
+    }
+}
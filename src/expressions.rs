@@ -1,17 +1,29 @@
 use crate::ast::Node;
+use crate::tokens::{Token, Span};
 
 #[derive(Debug)]
 pub enum Expression {
-    Prefix,
-    Infix,
+    Identifier { token: Token, span: Span },
 }
 
 impl Node for Expression {
     fn token_literal(&self) -> String {
-        todo!()
+        match self {
+            Expression::Identifier { token, .. } => token.string(),
+        }
     }
 
     fn string(&self) -> String {
-        todo!()
+        match self {
+            Expression::Identifier { token, .. } => token.string(),
+        }
+    }
+}
+
+impl Expression {
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Identifier { span, .. } => *span,
+        }
     }
 }
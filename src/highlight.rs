@@ -0,0 +1,183 @@
+use crate::tokens::{Token, Span};
+use crate::lexer;
+
+const RESET: &str = "\x1b[0m";
+const KEYWORD: &str = "\x1b[1;35m";
+const LITERAL: &str = "\x1b[33m";
+const OPERATOR: &str = "\x1b[36m";
+const IDENTIFIER: &str = "\x1b[34m";
+const ILLEGAL: &str = "\x1b[1;31m";
+const BRACKET: &str = "\x1b[1;4m";
+
+fn style(token: &Token) -> Option<&'static str> {
+    match token {
+        Token::Function | Token::Let | Token::If | Token::Else | Token::Return => Some(KEYWORD),
+        Token::Int(_) | Token::True | Token::False => Some(LITERAL),
+        Token::Assign | Token::Plus | Token::Minus | Token::Bang | Token::Asterisk
+        | Token::Slash | Token::Lt | Token::Gt | Token::Eq | Token::NotEq | Token::Arrow => Some(OPERATOR),
+        Token::Ident(_) => Some(IDENTIFIER),
+        Token::Illegal(_) => Some(ILLEGAL),
+        _ => None,
+    }
+}
+
+fn is_open(token: &Token) -> bool {
+    matches!(token, Token::LParen | Token::LBrace)
+}
+
+fn is_close(token: &Token) -> bool {
+    matches!(token, Token::RParen | Token::RBrace)
+}
+
+/// Index of the bracket matching the one at `index`, skipping nested pairs.
+fn matching_bracket(tokens: &[(Token, Span)], index: usize) -> Option<usize> {
+    let bracket = &tokens[index].0;
+    let mut depth = 0;
+
+    if is_open(bracket) {
+        for (i, (token, _)) in tokens.iter().enumerate().skip(index) {
+            if is_open(token) {
+                depth += 1;
+            } else if is_close(token) {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+        }
+    } else if is_close(bracket) {
+        for (i, (token, _)) in tokens.iter().enumerate().take(index + 1).rev() {
+            if is_close(token) {
+                depth += 1;
+            } else if is_open(token) {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Colors `line` by token class using ANSI escapes.
+///
+/// When `cursor` (a byte offset) is on or just after a bracket, that bracket
+/// and its match are underlined. The visible text is never changed.
+pub fn highlight(line: &str, cursor: Option<usize>) -> String {
+    // token spans count characters, the output is built from bytes
+    let mut offsets: Vec<usize> = line.char_indices().map(|(i, _)| i).collect();
+    offsets.push(line.len());
+
+    let tokens = lexer::tokens(line);
+
+    let mut brackets = vec![];
+    if let Some(cursor) = cursor {
+        let at = |offset: usize| tokens
+            .iter()
+            .position(|(token, span)| (is_open(token) || is_close(token)) && offsets[span.start] == offset);
+        let under = at(cursor).or_else(|| cursor.checked_sub(1).and_then(at));
+
+        if let Some(index) = under {
+            if let Some(other) = matching_bracket(&tokens, index) {
+                brackets.push(index);
+                brackets.push(other);
+            }
+        }
+    }
+
+    let mut out = String::new();
+    let mut last = 0;
+    for (i, (token, span)) in tokens.iter().enumerate() {
+        let start = offsets[span.start];
+        let end = offsets[span.end];
+        out.push_str(&line[last..start]);
+
+        let color = if brackets.contains(&i) { Some(BRACKET) } else { style(token) };
+        match color {
+            Some(color) => {
+                out.push_str(color);
+                out.push_str(&line[start..end]);
+                out.push_str(RESET);
+            },
+            None => out.push_str(&line[start..end]),
+        }
+        last = end;
+    }
+    out.push_str(&line[last..]);
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn paint(color: &str, text: &str) -> String {
+        format!("{}{}{}", color, text, RESET)
+    }
+
+    #[test]
+    fn token_classes() {
+        let line = "let x = 5 @@ true;";
+        let expected = [
+            paint(KEYWORD, "let"),
+            String::from(" "),
+            paint(IDENTIFIER, "x"),
+            String::from(" "),
+            paint(OPERATOR, "="),
+            String::from(" "),
+            paint(LITERAL, "5"),
+            String::from(" "),
+            paint(ILLEGAL, "@"),
+            paint(ILLEGAL, "@"),
+            String::from(" "),
+            paint(LITERAL, "true"),
+            String::from(";"),
+        ].join("");
+
+        assert_eq!(highlight(line, None), expected);
+    }
+
+    #[test]
+    fn matching_brackets() {
+        let line = "f(g(x))";
+        let expected = [
+            paint(IDENTIFIER, "f"),
+            paint(BRACKET, "("),
+            paint(IDENTIFIER, "g"),
+            String::from("("),
+            paint(IDENTIFIER, "x"),
+            String::from(")"),
+            paint(BRACKET, ")"),
+        ].join("");
+
+        // cursor on the opening bracket, and just after the closing one
+        assert_eq!(highlight(line, Some(1)), expected);
+        assert_eq!(highlight(line, Some(7)), expected);
+    }
+
+    #[test]
+    fn unmatched_bracket() {
+        let line = "{ x";
+        let expected = format!("{{ {}", paint(IDENTIFIER, "x"));
+
+        assert_eq!(highlight(line, Some(0)), expected);
+    }
+
+    #[test]
+    fn preserves_text() {
+        let line = "let  ünïcode\t= fn(a, b) { a + b };";
+        let stripped = highlight(line, Some(17))
+            .replace(RESET, "")
+            .replace(KEYWORD, "")
+            .replace(LITERAL, "")
+            .replace(OPERATOR, "")
+            .replace(IDENTIFIER, "")
+            .replace(ILLEGAL, "")
+            .replace(BRACKET, "");
+
+        assert_eq!(stripped, line);
+    }
+}
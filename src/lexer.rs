@@ -1,15 +1,17 @@
-use crate::tokens::Token;
+use crate::tokens::{Token, Span, Trivia, TriviaKind};
+use crate::diagnostics::Diagnostic;
 
 #[derive(Debug)]
 pub struct Lexer {
-    pub input: Vec<String>,
-    pub position: usize,
-    pub read_position: usize,
-    pub ch: String,
+    input: Vec<String>,
+    position: usize,
+    read_position: usize,
+    ch: String,
+    errors: Vec<Diagnostic>,
 }
 
 impl Lexer {
-    pub fn new(input: &String) -> Self {
+    pub fn new(input: &str) -> Self {
         let input_chars = input
             .chars()
             .map(|char| char.to_string())
@@ -20,14 +22,15 @@ impl Lexer {
             position: 0,
             read_position: 0,
             ch: String::from(""),
+            errors: vec![],
         };
         
         l.read_char();
         l
     }
 
-    pub fn read_char(&mut self) {
-        if self.read_position >= self.input.len().try_into().unwrap() {
+    fn read_char(&mut self) {
+        if self.read_position >= self.input.len() {
             self.ch = String::from("");
         } else {
             self.ch = self.input[self.read_position].to_owned();
@@ -38,12 +41,18 @@ impl Lexer {
     }
 
     pub fn next_token(&mut self) -> Token {
-        self.skip_white_space();
+        self.next_spanned_token().0
+    }
+
+    pub fn next_spanned_token(&mut self) -> (Token, Span) {
+        while !self.skip_trivia().is_empty() {}
+
+        let start = self.position.min(self.input.len());
 
         let character = self.ch.as_str();
         let token = match character {
             "=" => {
-                if Self::next_char_is_eq(&self) {
+                if self.next_char_is_eq() {
                     self.read_char();
                     Token::Eq
                 } else {
@@ -51,14 +60,22 @@ impl Lexer {
                 }
             },
             ";" => Token::SemiColon,
+            ":" => Token::Colon,
             "(" => Token::LParen,
             ")" => Token::RParen,
             "," => Token::Comma,
             "+" => Token::Plus,
-            "-" => Token::Minus,
+            "-" => {
+                if self.peek_char() == ">" {
+                    self.read_char();
+                    Token::Arrow
+                } else {
+                    Token::Minus
+                }
+            },
             "*" => Token::Asterisk,
             "!" => {
-                if Self::next_char_is_eq(&self) {
+                if self.next_char_is_eq() {
                     self.read_char();
                     Token::NotEq
                 } else {
@@ -77,36 +94,113 @@ impl Lexer {
                     Token::lookup_ident(&literal)
 
                 } else if Self::is_digit(&self.ch) {
-                    let literal = self.read_number();
-                    Token::Int(literal.into())
+                    match self.read_number() {
+                        Some(literal) => Token::Int(literal),
+                        None => {
+                            let lexeme = self.input[start..self.read_position].join("");
+                            self.errors.push(
+                                Diagnostic::error("integer literal out of range", Span::new(start, self.read_position))
+                                    .with_label(format!("does not fit in 0..={}", i32::MAX))
+                            );
+                            // reported above, not as an illegal character
+                            return self.finish_token(Token::Illegal(lexeme), start);
+                        }
+                    }
                 } else {
                     Token::Illegal(self.ch.to_string())
                 }
             }
         };
 
+        if let Token::Illegal(ch) = &token {
+            let span = Span::new(start, (self.position + 1).min(self.input.len()));
+            self.errors.push(
+                Diagnostic::error(format!("illegal character `{}`", ch), span)
+                    .with_label("not a valid Monkey token")
+            );
+        }
+
+        self.finish_token(token, start)
+    }
+
+    /// Spans the token from `start` to the current character and moves past it.
+    fn finish_token(&mut self, token: Token, start: usize) -> (Token, Span) {
+        let span = Span::new(start, (self.position + 1).min(self.input.len()));
         self.read_char();
-        token
+        (token, span)
+    }
+
+    /// Trivia-preserving mode: like `next_spanned_token`, but also returns the
+    /// whitespace and comments skipped before the token, so the input can be
+    /// rebuilt exactly.
+    pub fn next_token_with_trivia(&mut self) -> (Vec<Trivia>, Token, Span) {
+        let mut trivia = vec![];
+        loop {
+            let skipped = self.skip_trivia();
+            if skipped.is_empty() {
+                break;
+            }
+            trivia.extend(skipped);
+        }
+
+        let (token, span) = self.next_spanned_token();
+        (trivia, token, span)
+    }
+
+    /// Skips one run of whitespace followed by at most one comment.
+    fn skip_trivia(&mut self) -> Vec<Trivia> {
+        let mut trivia = vec![];
+
+        let start = self.position.min(self.input.len());
+        self.skip_white_space();
+        let end = self.position.min(self.input.len());
+        if end > start {
+            let span = Span::new(start, end);
+            trivia.push(Trivia { kind: TriviaKind::Whitespace, text: self.slice(span), span });
+        }
+
+        if self.ch == "/" && self.peek_char() == "/" {
+            let start = self.position;
+            while !self.ch.is_empty() && self.ch != "\n" && self.ch != "\r" {
+                self.read_char();
+            }
+            let span = Span::new(start, self.position.min(self.input.len()));
+            trivia.push(Trivia { kind: TriviaKind::Comment, text: self.slice(span), span });
+        }
+
+        trivia
     }
 
-    pub fn skip_white_space(&mut self) {
+    /// Problems found in the input so far, such as illegal characters.
+    pub fn errors(&self) -> &[Diagnostic] {
+        &self.errors
+    }
+
+    /// Exact source text covered by `span`.
+    pub fn slice(&self, span: Span) -> String {
+        let end = span.end.min(self.input.len());
+        let start = span.start.min(end);
+        self.input[start..end].join("")
+    }
+
+    fn skip_white_space(&mut self) {
         while " " == self.ch  || "\t" == self.ch || "\n" == self.ch || "\r" == self.ch {
             self.read_char()
         }
     }
 
-    pub fn is_letter(letter: &String) -> bool {
+    pub(crate) fn is_letter(letter: &str) -> bool {
         let ident_characters = String::from(
             "qwertyuiopasdfghjklzxcvbnmQWERTYUIOPASDFGHJKLZXCVBNM_"
         );
 
-        ident_characters.contains(letter)
+        !letter.is_empty() && ident_characters.contains(letter)
     }
 
-    pub fn read_identifier(&mut self) -> String {
+    fn read_identifier(&mut self) -> String {
         let position = self.position;
 
-        while Self::is_letter(&self.input[self.read_position]) {
+        while Self::is_letter(self.peek_char()) {
             self.read_char();
 
         };
@@ -114,24 +208,48 @@ impl Lexer {
         self.input[position..self.read_position].join("")
     }
 
-    pub fn is_digit(character: &String) -> bool {
-        "1234567890".contains(character)
+    fn is_digit(character: &str) -> bool {
+        !character.is_empty() && "1234567890".contains(character)
     }
 
-    pub fn read_number(&mut self) -> i32 {
+    /// Reads a run of digits; `None` when it does not fit in an `i32`.
+    fn read_number(&mut self) -> Option<i32> {
         let position = self.position;
 
-        while Self::is_digit(&self.input[self.read_position]) {
+        while Self::is_digit(self.peek_char()) {
             self.read_char();
         };
-        self.input[position..self.read_position].join("").parse::<i32>().unwrap()
+        self.input[position..self.read_position].join("").parse::<i32>().ok()
     }
 
-    pub fn next_char_is_eq(&self) -> bool {
-        self.input[self.read_position] == "="
+    fn next_char_is_eq(&self) -> bool {
+        self.peek_char() == "="
+    }
+
+    fn peek_char(&self) -> &str {
+        self.input
+            .get(self.read_position)
+            .map(|ch| ch.as_str())
+            .unwrap_or("")
     }
 }
 
+/// Every token of `input` with its span, without trivia or the final `Eof`.
+pub(crate) fn tokens(input: &str) -> Vec<(Token, Span)> {
+    let mut l = Lexer::new(input);
+    let mut tokens = vec![];
+
+    loop {
+        let (token, span) = l.next_spanned_token();
+        if token == Token::Eof {
+            break;
+        }
+        tokens.push((token, span));
+    }
+
+    tokens
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -316,4 +434,144 @@ if (5 < 10) {
             assert_eq!(tok, token);
         }
     }
+
+    #[test]
+    fn integer_out_of_range() {
+        let input = "let big = 99999999999; 2147483647";
+        let mut l = Lexer::new(input);
+
+        let tokens: Vec<(Token, Span)> = (0..6).map(|_| l.next_spanned_token()).collect();
+        assert_eq!(tokens[3], (Token::Illegal(String::from("99999999999")), Span::new(10, 21)));
+        assert_eq!(tokens[4], (Token::SemiColon, Span::new(21, 22)));
+        assert_eq!(tokens[5], (Token::Int(i32::MAX), Span::new(23, 33)));
+
+        assert_eq!(l.errors.len(), 1, "Expected one error, got {:?}", l.errors);
+        assert_eq!(l.errors[0].message, "integer literal out of range");
+        assert_eq!(l.errors[0].span, Span::new(10, 21));
+        assert_eq!(l.errors[0].label.as_deref(), Some("does not fit in 0..=2147483647"));
+    }
+
+    #[test]
+    fn annotations() {
+        let input = "let x: int = 5;\nfn(a: int) -> bool { a - 1 }";
+
+        let tokens = vec![
+            Token::Let,
+            Token::Ident(String::from("x")),
+            Token::Colon,
+            Token::Ident(String::from("int")),
+            Token::Assign,
+            Token::Int(5),
+            Token::SemiColon,
+            Token::Function,
+            Token::LParen,
+            Token::Ident(String::from("a")),
+            Token::Colon,
+            Token::Ident(String::from("int")),
+            Token::RParen,
+            Token::Arrow,
+            Token::Ident(String::from("bool")),
+            Token::LBrace,
+            Token::Ident(String::from("a")),
+            Token::Minus,
+            Token::Int(1),
+            Token::RBrace,
+            Token::Eof,
+        ];
+
+        let mut l = Lexer::new(input);
+
+        for token in tokens {
+            assert_eq!(l.next_token(), token);
+        }
+    }
+
+    #[test]
+    fn spans() {
+        let input = String::from("\
+let ten = 10;
+ten != @;");
+
+        let tokens = vec![
+            (Token::Let, Span::new(0, 3)),
+            (Token::Ident(String::from("ten")), Span::new(4, 7)),
+            (Token::Assign, Span::new(8, 9)),
+            (Token::Int(10), Span::new(10, 12)),
+            (Token::SemiColon, Span::new(12, 13)),
+            (Token::Ident(String::from("ten")), Span::new(14, 17)),
+            (Token::NotEq, Span::new(18, 20)),
+            (Token::Illegal(String::from("@")), Span::new(21, 22)),
+            (Token::SemiColon, Span::new(22, 23)),
+            (Token::Eof, Span::new(23, 23)),
+        ];
+
+        let mut l = Lexer::new(&input);
+
+        for token in tokens {
+            let tok = l.next_spanned_token();
+            assert_eq!(tok, token);
+        }
+
+        assert_eq!(l.errors.len(), 1, "Expected one lexer error, got {:?}", l.errors);
+        assert_eq!(l.errors[0].span, Span::new(21, 22));
+    }
+
+    #[test]
+    fn trivia() {
+        let input = String::from("let  x =\t5;\r\n");
+
+        let tokens = vec![
+            (vec![], Token::Let, "let"),
+            (vec!["  "], Token::Ident(String::from("x")), "x"),
+            (vec![" "], Token::Assign, "="),
+            (vec!["\t"], Token::Int(5), "5"),
+            (vec![], Token::SemiColon, ";"),
+            (vec!["\r\n"], Token::Eof, ""),
+        ];
+
+        let mut l = Lexer::new(&input);
+        let mut rebuilt = String::new();
+
+        for (trivia, token, text) in tokens {
+            let (tok_trivia, tok, span) = l.next_token_with_trivia();
+            let trivia_text: Vec<&str> = tok_trivia.iter().map(|t| t.text.as_str()).collect();
+
+            assert_eq!(trivia_text, trivia);
+            assert_eq!(tok, token);
+            assert_eq!(l.slice(span), text);
+
+            for t in tok_trivia {
+                rebuilt.push_str(&t.text);
+            }
+            rebuilt.push_str(&l.slice(span));
+        }
+
+        assert_eq!(rebuilt, input);
+    }
+
+    #[test]
+    fn comments() {
+        let input = "// header\nlet x = 10 / 2; // half\r\n//";
+        let mut l = Lexer::new(input);
+
+        let (trivia, token, _) = l.next_token_with_trivia();
+        assert_eq!(token, Token::Let);
+        assert_eq!(trivia[0], Trivia { kind: TriviaKind::Comment, text: String::from("// header"), span: Span::new(0, 9) });
+        assert_eq!(trivia[1].kind, TriviaKind::Whitespace);
+
+        let tokens: Vec<Token> = (0..6).map(|_| l.next_token()).collect();
+        assert_eq!(tokens, vec![
+            Token::Ident(String::from("x")),
+            Token::Assign,
+            Token::Int(10),
+            Token::Slash,
+            Token::Int(2),
+            Token::SemiColon,
+        ]);
+
+        let (trivia, token, _) = l.next_token_with_trivia();
+        let texts: Vec<&str> = trivia.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, vec![" ", "// half", "\r\n", "//"]);
+        assert_eq!(token, Token::Eof);
+    }
 }
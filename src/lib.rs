@@ -1,7 +1,38 @@
+//! The Monkey programming language.
+//!
+//! The lexer, parser and AST are public so tools can work with Monkey
+//! source directly:
+//!
+//! ```
+//! use interpreter::{Lexer, Node, Parser};
+//!
+//! let mut parser = Parser::new(Lexer::new("let x = 5;\nx;"));
+//! let program = parser.parse_program();
+//!
+//! assert!(parser.errors().is_empty());
+//! assert_eq!(program.statements.len(), 2);
+//! assert_eq!(program.statements[1].string(), "x");
+//! ```
 #![allow(dead_code)]
-mod tokens;
-mod lexer;
-mod ast;
-mod parser;
+#![allow(clippy::upper_case_acronyms)]
+pub mod tokens;
+pub mod lexer;
+pub mod ast;
+pub mod parser;
 pub mod repl;
-mod expressions;
+pub mod expressions;
+pub mod diagnostics;
+pub mod cst;
+pub mod export;
+pub mod highlight;
+pub mod transcript;
+pub mod lsp;
+pub mod resolve;
+pub mod lint;
+
+pub use tokens::{Token, Span, Trivia, TriviaKind};
+pub use lexer::Lexer;
+pub use ast::{Node, Statement, LetStatement, ReturnStatement, ExpressionStatement, Program, TypeAnnotation, Visitor, Fold};
+pub use expressions::Expression;
+pub use parser::Parser;
+pub use diagnostics::{Diagnostic, Severity, Label};
@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use crate::tokens::{Token, Span, TriviaKind};
+use crate::lexer::{self, Lexer};
+use crate::ast::{Program, Statement};
+use crate::diagnostics::{Diagnostic, LineIndex, Severity};
+
+/// A lint check, identified by `id` in config files and `lint:allow`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub id: &'static str,
+    pub description: &'static str,
+    pub default: Severity,
+}
+
+pub const UNREACHABLE_CODE: Rule = Rule {
+    id: "unreachable-code",
+    description: "statements after a `return`",
+    default: Severity::Warning,
+};
+
+pub const BOOL_COMPARISON: Rule = Rule {
+    id: "bool-comparison",
+    description: "comparison with `true` or `false`",
+    default: Severity::Warning,
+};
+
+pub const EMPTY_BLOCK: Rule = Rule {
+    id: "empty-block",
+    description: "an `if`, `else` or function body with nothing in it",
+    default: Severity::Warning,
+};
+
+pub const CONSTANT_CONDITION: Rule = Rule {
+    id: "constant-condition",
+    description: "an `if` whose condition is a literal",
+    default: Severity::Warning,
+};
+
+pub const RULES: [&Rule; 4] = [&UNREACHABLE_CODE, &BOOL_COMPARISON, &EMPTY_BLOCK, &CONSTANT_CONDITION];
+
+/// Per-rule severities. `None` turns a rule off.
+///
+/// The file format has one `rule-id = allow|warning|error` per line; blank
+/// lines and lines starting with `#` are ignored.
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct Config {
+    levels: HashMap<String, Option<Severity>>,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut levels = HashMap::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (id, level) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected `rule = level`", number + 1))?;
+            let (id, level) = (id.trim(), level.trim());
+
+            if !RULES.iter().any(|rule| rule.id == id) {
+                return Err(format!("line {}: unknown rule `{}`", number + 1, id));
+            }
+            let level = match level {
+                "allow" => None,
+                "warning" => Some(Severity::Warning),
+                "error" => Some(Severity::Error),
+                _ => {
+                    return Err(format!("line {}: unknown level `{}`, expected allow, warning or error", number + 1, level))
+                },
+            };
+
+            levels.insert(id.to_string(), level);
+        }
+
+        Ok(Config { levels })
+    }
+
+    pub fn severity(&self, rule: &Rule) -> Option<Severity> {
+        self.levels.get(rule.id).copied().unwrap_or(Some(rule.default))
+    }
+}
+
+/// Runs every enabled rule over `program`, parsed from `source`.
+///
+/// A `// lint:allow(rule, ...)` comment silences those rules on its own line
+/// and the line after it.
+pub fn lint(source: &str, program: &Program, config: &Config) -> Vec<Diagnostic> {
+    let tokens = lexer::tokens(source);
+    let mut found = vec![];
+    unreachable_code(program, &mut found);
+    bool_comparison(&tokens, &mut found);
+    empty_block(&tokens, &mut found);
+    constant_condition(&tokens, &mut found);
+
+    let lines = LineIndex::new(source);
+    let (allowed, mut diagnostics) = suppressions(source, &lines);
+
+    for (rule, diagnostic) in found {
+        let Some(severity) = config.severity(rule) else {
+            continue;
+        };
+
+        let (line, _) = lines.location(diagnostic.span.start);
+        let suppressed = allowed
+            .iter()
+            .any(|(id, comment_line)| id == rule.id && (line == *comment_line || line == comment_line + 1));
+        if !suppressed {
+            diagnostics.push(Diagnostic { severity, ..diagnostic.with_code(rule.id) });
+        }
+    }
+
+    diagnostics.sort_by_key(|d| d.span.start);
+    diagnostics
+}
+
+fn unreachable_code(program: &Program, found: &mut Vec<(&'static Rule, Diagnostic)>) {
+    let statements = &program.statements;
+    let Some(index) = statements.iter().position(|s| matches!(s, Statement::Return(_))) else {
+        return;
+    };
+
+    if let (Some(first), Some(last)) = (statements.get(index + 1), statements.last()) {
+        found.push((
+            &UNREACHABLE_CODE,
+            Diagnostic::warning("unreachable statement", first.span().to(last.span()))
+                .with_label("never runs")
+                .with_secondary(statements[index].span(), "any code after this `return` is unreachable"),
+        ));
+    }
+}
+
+// `let` values, blocks and `if` are not in the AST yet, so the rules below
+// read the tokens.
+fn bool_comparison(tokens: &[(Token, Span)], found: &mut Vec<(&'static Rule, Diagnostic)>) {
+    for window in tokens.windows(2) {
+        let [(left, left_span), (right, right_span)] = window else {
+            continue;
+        };
+
+        let literal = match (left, right) {
+            (Token::Eq | Token::NotEq, Token::True | Token::False) => right,
+            (Token::True | Token::False, Token::Eq | Token::NotEq) => left,
+            _ => continue,
+        };
+
+        found.push((
+            &BOOL_COMPARISON,
+            Diagnostic::warning(format!("comparison with `{}`", literal.string()), left_span.to(*right_span))
+                .with_label("redundant comparison")
+                .with_help("use the value directly, or negate it with `!`"),
+        ));
+    }
+}
+
+// only braces after `)` or `else` open a block; others start a hash literal
+fn empty_block(tokens: &[(Token, Span)], found: &mut Vec<(&'static Rule, Diagnostic)>) {
+    for window in tokens.windows(3) {
+        let [(before, _), (Token::LBrace, left_span), (Token::RBrace, right_span)] = window else {
+            continue;
+        };
+        if !matches!(before, Token::RParen | Token::Else) {
+            continue;
+        }
+
+        found.push((
+            &EMPTY_BLOCK,
+            Diagnostic::warning("empty block", left_span.to(*right_span))
+                .with_label("this block does nothing"),
+        ));
+    }
+}
+
+fn constant_condition(tokens: &[(Token, Span)], found: &mut Vec<(&'static Rule, Diagnostic)>) {
+    for window in tokens.windows(4) {
+        let [(Token::If, if_span), (Token::LParen, _), (literal, span), (Token::RParen, _)] = window else {
+            continue;
+        };
+
+        // only `false` is falsy among literals, so `0` is always true as well
+        let label = match literal {
+            Token::False => "always false",
+            Token::True | Token::Int(_) => "always true",
+            _ => continue,
+        };
+
+        found.push((
+            &CONSTANT_CONDITION,
+            Diagnostic::warning("constant `if` condition", *span)
+                .with_label(label)
+                .with_secondary(*if_span, "so only one branch of this `if` can run"),
+        ));
+    }
+}
+
+/// Rules allowed by `lint:allow` comments with the line of each comment,
+/// and warnings for rule IDs that do not exist.
+fn suppressions(source: &str, lines: &LineIndex) -> (Vec<(String, usize)>, Vec<Diagnostic>) {
+    let mut allowed = vec![];
+    let mut unknown = vec![];
+
+    let mut l = Lexer::new(source);
+    loop {
+        let (trivia, token, _) = l.next_token_with_trivia();
+
+        for comment in trivia.iter().filter(|t| t.kind == TriviaKind::Comment) {
+            let ids = comment.text
+                .trim_start_matches('/')
+                .trim()
+                .strip_prefix("lint:allow(")
+                .and_then(|rest| rest.strip_suffix(')'));
+
+            for id in ids.into_iter().flat_map(|ids| ids.split(',')).map(str::trim) {
+                if RULES.iter().any(|rule| rule.id == id) {
+                    allowed.push((id.to_string(), lines.location(comment.span.start).0));
+                } else {
+                    unknown.push(
+                        Diagnostic::warning(format!("unknown lint rule `{}`", id), comment.span)
+                            .with_label("in this comment"),
+                    );
+                }
+            }
+        }
+
+        if token == Token::Eof {
+            break;
+        }
+    }
+
+    (allowed, unknown)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn lint_source(input: &str, config: &Config) -> Vec<Diagnostic> {
+        let mut p = Parser::new(Lexer::new(input));
+        let program = p.parse_program();
+        assert!(p.errors().is_empty(), "Parser errors {:?}", p.errors());
+        lint(input, &program, config)
+    }
+
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<(Severity, String, Span)> {
+        diagnostics
+            .iter()
+            .map(|d| (d.severity, d.code.clone().unwrap_or_default(), d.span))
+            .collect()
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let diagnostics = lint_source("let x = 1;\nreturn x;\nx;\nlet y = 2;", &Config::default());
+
+        assert_eq!(codes(&diagnostics), vec![
+            (Severity::Warning, String::from("unreachable-code"), Span::new(21, 34)),
+        ]);
+        assert_eq!(diagnostics[0].secondary[0].span, Span::new(11, 20));
+
+        let rendered = diagnostics[0].render("test.mk", "let x = 1;\nreturn x;\nx;\nlet y = 2;", false);
+        assert!(rendered.starts_with("warning[unreachable-code]: unreachable statement\n"), "Unexpected output {}", rendered);
+    }
+
+    #[test]
+    fn bool_comparison() {
+        let diagnostics = lint_source("let a = x == true;\nlet b = false != y;\nlet c = x == y;", &Config::default());
+
+        assert_eq!(codes(&diagnostics), vec![
+            (Severity::Warning, String::from("bool-comparison"), Span::new(10, 17)),
+            (Severity::Warning, String::from("bool-comparison"), Span::new(27, 35)),
+        ]);
+        assert_eq!(diagnostics[0].message, "comparison with `true`");
+    }
+
+    #[test]
+    fn empty_block() {
+        let diagnostics = lint_source("let a = if (x) { } else { y };\nlet f = fn() {};\nlet h = {};", &Config::default());
+
+        assert_eq!(codes(&diagnostics), vec![
+            (Severity::Warning, String::from("empty-block"), Span::new(15, 18)),
+            (Severity::Warning, String::from("empty-block"), Span::new(44, 46)),
+        ]);
+    }
+
+    #[test]
+    fn constant_condition() {
+        let input = "let a = if (true) { x } else { y };\nlet b = if (0) { x };\nlet c = if (x) { y };";
+        let diagnostics = lint_source(input, &Config::default());
+
+        assert_eq!(codes(&diagnostics), vec![
+            (Severity::Warning, String::from("constant-condition"), Span::new(12, 16)),
+            (Severity::Warning, String::from("constant-condition"), Span::new(48, 49)),
+        ]);
+        assert_eq!(diagnostics[0].label.as_deref(), Some("always true"));
+        assert_eq!(diagnostics[0].secondary[0].span, Span::new(8, 10));
+    }
+
+    #[test]
+    fn config() {
+        let config = Config::parse("# project rules\nunreachable-code = error\n\nbool-comparison = allow\n").unwrap();
+        let diagnostics = lint_source("return x == true;\nx;", &config);
+
+        assert_eq!(codes(&diagnostics), vec![
+            (Severity::Error, String::from("unreachable-code"), Span::new(18, 20)),
+        ]);
+
+        assert_eq!(Config::parse("nope = error"), Err(String::from("line 1: unknown rule `nope`")));
+        assert_eq!(
+            Config::parse("\nbool-comparison = loud"),
+            Err(String::from("line 2: unknown level `loud`, expected allow, warning or error")),
+        );
+        assert_eq!(Config::parse("bool-comparison"), Err(String::from("line 1: expected `rule = level`")));
+    }
+
+    #[test]
+    fn allow_comments() {
+        let input = "\
+let a = x == true; // lint:allow(bool-comparison)
+// lint:allow(unreachable-code, bool-comparison)
+return a != false;
+a;
+let b = y == false; // lint:allow(typo)
+";
+        let diagnostics = lint_source(input, &Config::default());
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+
+        assert_eq!(messages, vec![
+            "unreachable statement",
+            "comparison with `false`",
+            "unknown lint rule `typo`",
+        ]);
+    }
+}
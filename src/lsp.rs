@@ -0,0 +1,672 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+use serde_json::{json, Value};
+use crate::tokens::{Token, Span};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::diagnostics::{Diagnostic, LineIndex, Severity};
+use crate::resolve;
+use crate::cst::{self, NodeKind, Statement, SyntaxNode, SyntaxToken};
+
+// LSP enum values used below
+const SYNC_FULL: u8 = 1;
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+const SYMBOL_FUNCTION: u8 = 12;
+const SYMBOL_VARIABLE: u8 = 13;
+const COMPLETION_VARIABLE: u8 = 6;
+const COMPLETION_KEYWORD: u8 = 14;
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Largest message body accepted, in bytes; longer ones are skipped.
+const MAX_LENGTH: usize = 16 * 1024 * 1024;
+
+/// Serves the Language Server Protocol over `input` and `output` until the
+/// client sends `exit` or closes the stream.
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut server = Server::default();
+
+    // a malformed message gets an error reply and the server carries on; its
+    // id cannot be trusted, so the reply has a null id as JSON-RPC requires
+    while let Some(frame) = read_frame(&mut input)? {
+        let replies = match frame {
+            Frame::Body(body) => match serde_json::from_slice::<Value>(&body) {
+                Ok(message) if message.is_object() => server.handle(&message),
+                Ok(_) => vec![error(INVALID_REQUEST, "Invalid request: expected a JSON object")],
+                Err(e) => vec![error(PARSE_ERROR, &format!("Parse error: {}", e))],
+            },
+            Frame::MissingLength => vec![error(PARSE_ERROR, "Parse error: missing Content-Length header")],
+            Frame::TooLong(length) => vec![error(
+                PARSE_ERROR,
+                &format!("Parse error: message of {} bytes is over the {} byte limit", length, MAX_LENGTH),
+            )],
+        };
+
+        for reply in replies {
+            write_message(&mut output, &reply)?;
+        }
+        if server.exit {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads one `Content-Length` framed JSON-RPC message.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    match read_frame(input)? {
+        Some(Frame::Body(body)) => serde_json::from_slice(&body)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        Some(Frame::MissingLength) => Err(io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length")),
+        Some(Frame::TooLong(_)) => Err(io::Error::new(io::ErrorKind::InvalidData, "message too long")),
+        None => Ok(None),
+    }
+}
+
+enum Frame {
+    Body(Vec<u8>),
+    // headers ended without a length, so no body could be read
+    MissingLength,
+    // over MAX_LENGTH, so the body was skipped rather than read
+    TooLong(usize),
+}
+
+fn read_frame(input: &mut impl BufRead) -> io::Result<Option<Frame>> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let Some(length) = length else {
+        return Ok(Some(Frame::MissingLength));
+    };
+    if length > MAX_LENGTH {
+        io::copy(&mut Read::take(&mut *input, length as u64), &mut io::sink())?;
+        return Ok(Some(Frame::TooLong(length)));
+    }
+
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+
+    Ok(Some(Frame::Body(body)))
+}
+
+fn error(code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": Value::Null,
+        "error": { "code": code, "message": message },
+    })
+}
+
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<String, String>,
+    exit: bool,
+}
+
+impl Server {
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        let id = message.get("id").cloned();
+
+        let result = match method {
+            "initialize" => Some(json!({
+                "capabilities": {
+                    "textDocumentSync": SYNC_FULL,
+                    "documentSymbolProvider": true,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "completionProvider": {},
+                },
+                "serverInfo": { "name": "monkey" },
+            })),
+            "shutdown" => Some(Value::Null),
+            "exit" => {
+                self.exit = true;
+                return vec![];
+            },
+            "textDocument/didOpen" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+                let text = params["textDocument"]["text"].as_str().unwrap_or("").to_string();
+                return vec![self.update(uri, text)];
+            },
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+                // full sync: the last change holds the whole document
+                let changes = params["contentChanges"].as_array();
+                match changes.and_then(|c| c.last()).and_then(|c| c["text"].as_str()) {
+                    Some(text) => return vec![self.update(uri, text.to_string())],
+                    None => return vec![],
+                }
+            },
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+                self.documents.remove(&uri);
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": { "uri": uri, "diagnostics": [] },
+                })];
+            },
+            "textDocument/documentSymbol" => self.document(params).map(|d| d.symbols()),
+            "textDocument/hover" => self.document(params).map(|d| d.hover(&params["position"])),
+            "textDocument/definition" => self.document(params).map(|d| d.definition(&params["position"])),
+            "textDocument/completion" => self.document(params).map(|d| d.completion()),
+            _ => None,
+        };
+
+        // notifications have no id and get no response
+        let id = match id {
+            Some(id) => id,
+            None => return vec![],
+        };
+
+        match result {
+            Some(result) => vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })],
+            None if method.starts_with("textDocument/") => {
+                vec![json!({ "jsonrpc": "2.0", "id": id, "result": Value::Null })]
+            },
+            None => vec![json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": METHOD_NOT_FOUND, "message": format!("unknown method {}", method) },
+            })],
+        }
+    }
+
+    fn update(&mut self, uri: String, text: String) -> Value {
+        let diagnostics: Vec<Value> = Document::new(&uri, &text).diagnostics();
+        self.documents.insert(uri.clone(), text);
+
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        })
+    }
+
+    fn document<'a>(&'a self, params: &'a Value) -> Option<Document<'a>> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let text = self.documents.get(uri)?;
+        Some(Document::new(uri, text))
+    }
+}
+
+/// An open file, with positions translated between LSP line/UTF-16 column
+/// pairs and the character offsets used by spans.
+struct Document<'a> {
+    uri: &'a str,
+    text: &'a str,
+    lines: LineIndex,
+    chars: Vec<char>,
+}
+
+impl<'a> Document<'a> {
+    fn new(uri: &'a str, text: &'a str) -> Self {
+        Document { uri, text, lines: LineIndex::new(text), chars: text.chars().collect() }
+    }
+
+    fn position(&self, offset: usize) -> Value {
+        let offset = offset.min(self.chars.len());
+        let (line, column) = self.lines.location(offset);
+        let character: usize = self.chars[offset - column..offset].iter().map(|c| c.len_utf16()).sum();
+
+        json!({ "line": line, "character": character })
+    }
+
+    fn offset(&self, position: &Value) -> usize {
+        let line = position["line"].as_u64().unwrap_or(0) as usize;
+        let character = position["character"].as_u64().unwrap_or(0) as usize;
+
+        let Some(start) = self.lines.start(line) else {
+            return self.chars.len();
+        };
+
+        let mut offset = start;
+        let mut units = 0;
+        while offset < self.chars.len() && self.chars[offset] != '\n' && units < character {
+            units += self.chars[offset].len_utf16();
+            offset += 1;
+        }
+        offset
+    }
+
+    fn range(&self, span: Span) -> Value {
+        json!({ "start": self.position(span.start), "end": self.position(span.end) })
+    }
+
+    fn diagnostics(&self) -> Vec<Value> {
+        let mut p = Parser::new(Lexer::new(self.text));
+        let program = p.parse_program();
+
+        let mut found = p.errors().to_vec();
+        found.extend(resolve::resolve(&program).diagnostics);
+        found.sort_by_key(|d| d.span.start);
+
+        found.iter().map(|d| self.diagnostic(d)).collect()
+    }
+
+    fn diagnostic(&self, diagnostic: &Diagnostic) -> Value {
+        let severity = match diagnostic.severity {
+            Severity::Error => SEVERITY_ERROR,
+            Severity::Warning => SEVERITY_WARNING,
+        };
+
+        let mut message = diagnostic.message.clone();
+        for help in &diagnostic.help {
+            message.push_str(&format!("\nhelp: {}", help));
+        }
+
+        let related: Vec<Value> = diagnostic.secondary.iter().map(|label| json!({
+            "location": { "uri": self.uri, "range": self.range(label.span) },
+            "message": label.message,
+        })).collect();
+
+        let mut value = json!({
+            "range": self.range(diagnostic.span),
+            "severity": severity,
+            "source": "monkey",
+            "message": message,
+            "relatedInformation": related,
+        });
+        if let Some(code) = &diagnostic.code {
+            value["code"] = json!(code);
+        }
+        value
+    }
+
+    fn symbols(&self) -> Value {
+        let tree = cst::parse(self.text);
+        let bindings = bindings(&tree);
+
+        let symbols: Vec<Value> = bindings.iter().map(|binding| {
+            let kind = binding_kind(&bindings, binding);
+            json!({
+                "name": binding.name.text,
+                "detail": kind,
+                "kind": if kind == "function" { SYMBOL_FUNCTION } else { SYMBOL_VARIABLE },
+                "range": self.range(binding.statement.span()),
+                "selectionRange": self.range(binding.name.span),
+            })
+        }).collect();
+
+        json!(symbols)
+    }
+
+    fn hover(&self, position: &Value) -> Value {
+        let tree = cst::parse(self.text);
+        let bindings = bindings(&tree);
+
+        let Some(binding) = self.resolve(&tree, &bindings, position) else {
+            return Value::Null;
+        };
+
+        json!({
+            "contents": {
+                "kind": "markdown",
+                "value": format!("```monkey\nlet {}\n```\n{}", binding.name.text, binding_kind(&bindings, binding)),
+            },
+        })
+    }
+
+    fn definition(&self, position: &Value) -> Value {
+        let tree = cst::parse(self.text);
+        let bindings = bindings(&tree);
+
+        match self.resolve(&tree, &bindings, position) {
+            Some(binding) => json!({ "uri": self.uri, "range": self.range(binding.name.span) }),
+            None => Value::Null,
+        }
+    }
+
+    fn completion(&self) -> Value {
+        let tree = cst::parse(self.text);
+        let mut items: Vec<Value> = Token::KEYWORDS
+            .iter()
+            .map(|keyword| json!({ "label": keyword, "kind": COMPLETION_KEYWORD }))
+            .collect();
+
+        let mut seen = vec![];
+        for binding in bindings(&tree) {
+            if !seen.contains(&binding.name.text) {
+                seen.push(binding.name.text.clone());
+                items.push(json!({ "label": binding.name.text, "kind": COMPLETION_VARIABLE }));
+            }
+        }
+
+        json!(items)
+    }
+
+    /// The binding an identifier at `position` refers to: the closest `let`
+    /// of that name before it. A use before any `let` refers to nothing.
+    fn resolve<'t>(&self, tree: &SyntaxNode, bindings: &'t [Binding<'t>], position: &Value) -> Option<&'t Binding<'t>> {
+        let offset = self.offset(position);
+        let token = tree
+            .tokens()
+            .into_iter()
+            .find(|t| t.span.start <= offset && offset <= t.span.end && matches!(t.token, Token::Ident(_)))?;
+
+        bindings
+            .iter()
+            .rev()
+            .find(|b| b.name.text == token.text && b.name.span.start <= token.span.start)
+    }
+}
+
+struct Binding<'t> {
+    name: &'t SyntaxToken,
+    statement: &'t SyntaxNode,
+    value: Option<&'t SyntaxNode>,
+}
+
+fn bindings(tree: &SyntaxNode) -> Vec<Binding<'_>> {
+    let Some(program) = cst::Program::cast(tree) else {
+        return vec![];
+    };
+
+    program
+        .statements()
+        .filter_map(|statement| match statement {
+            Statement::Let(s) => Some(Binding { name: s.name(), statement: statement.syntax(), value: s.value() }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A best guess at what a binding holds, from the tokens of its value.
+fn binding_kind(bindings: &[Binding], binding: &Binding) -> &'static str {
+    let mut binding = binding;
+
+    // follow `let a = b;` chains, at most once per binding to avoid cycles
+    for _ in 0..=bindings.len() {
+        let Some(value) = binding.value else {
+            return "unknown";
+        };
+        let tokens = value.tokens();
+
+        match (value.kind, tokens.first().map(|t| &t.token)) {
+            (NodeKind::Identifier, _) => {
+                let name = &tokens[0].text;
+                let target = bindings
+                    .iter()
+                    .rev()
+                    .find(|b| &b.name.text == name && b.name.span.start < binding.name.span.start);
+                match target {
+                    Some(target) => binding = target,
+                    None => return "unknown",
+                }
+            },
+            (_, Some(Token::Function)) => return "function",
+            (_, Some(Token::True | Token::False | Token::Bang)) => return "boolean",
+            (_, Some(Token::Int(_) | Token::Minus)) if tokens.iter().all(|t| is_arithmetic(&t.token)) => {
+                return "integer"
+            },
+            _ => return "unknown",
+        }
+    }
+
+    "unknown"
+}
+
+fn is_arithmetic(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Int(_) | Token::Plus | Token::Minus | Token::Asterisk | Token::Slash | Token::LParen | Token::RParen
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A stub client: frames `messages`, runs the server over them and
+    /// returns everything it sent back.
+    fn exchange(messages: &[Value]) -> Vec<Value> {
+        let mut input = vec![];
+        for message in messages {
+            write_message(&mut input, message).unwrap();
+        }
+
+        let mut output = vec![];
+        run(Cursor::new(input), &mut output).unwrap();
+
+        let mut replies = vec![];
+        let mut reader = Cursor::new(output);
+        while let Some(reply) = read_message(&mut reader).unwrap() {
+            replies.push(reply);
+        }
+        replies
+    }
+
+    fn request(id: u64, method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    fn notification(method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "method": method, "params": params })
+    }
+
+    fn open(text: &str) -> Value {
+        notification("textDocument/didOpen", json!({
+            "textDocument": { "uri": "file:///test.mk", "languageId": "monkey", "version": 1, "text": text },
+        }))
+    }
+
+    fn at(id: u64, method: &str, line: u64, character: u64) -> Value {
+        request(id, method, json!({
+            "textDocument": { "uri": "file:///test.mk" },
+            "position": { "line": line, "character": character },
+        }))
+    }
+
+    fn result(replies: &[Value], id: u64) -> Value {
+        replies
+            .iter()
+            .find(|r| r["id"] == json!(id))
+            .map(|r| r["result"].clone())
+            .unwrap_or_else(|| panic!("No reply to request {} in {:?}", id, replies))
+    }
+
+    #[test]
+    fn initialize_and_shutdown() {
+        let replies = exchange(&[
+            request(1, "initialize", json!({ "capabilities": {} })),
+            notification("initialized", json!({})),
+            request(2, "shutdown", Value::Null),
+            notification("exit", Value::Null),
+            request(3, "never/answered", Value::Null),
+        ]);
+
+        assert_eq!(replies.len(), 2, "Unexpected replies {:?}", replies);
+        assert_eq!(result(&replies, 1)["capabilities"]["textDocumentSync"], json!(1));
+        assert_eq!(result(&replies, 2), Value::Null);
+    }
+
+    #[test]
+    fn malformed_messages() {
+        let mut input = vec![];
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"initialize",}"#;
+        write!(input, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        let body = r#"{"id": "abc", oops"#;
+        write!(input, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        write!(input, "Content-Type: application/json\r\n\r\n").unwrap();
+        let body = " ".repeat(MAX_LENGTH + 1);
+        write!(input, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        let body = "[1,2]";
+        write!(input, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        write_message(&mut input, &request(2, "shutdown", Value::Null)).unwrap();
+
+        let mut output = vec![];
+        run(Cursor::new(input), &mut output).unwrap();
+
+        let mut replies = vec![];
+        let mut reader = Cursor::new(output);
+        while let Some(reply) = read_message(&mut reader).unwrap() {
+            replies.push(reply);
+        }
+
+        let errors: Vec<(Value, Value)> = replies
+            .iter()
+            .take(5)
+            .map(|r| (r["id"].clone(), r["error"]["code"].clone()))
+            .collect();
+        assert_eq!(errors, vec![
+            (Value::Null, json!(PARSE_ERROR)),
+            (Value::Null, json!(PARSE_ERROR)),
+            (Value::Null, json!(PARSE_ERROR)),
+            (Value::Null, json!(PARSE_ERROR)),
+            (Value::Null, json!(INVALID_REQUEST)),
+        ]);
+        assert_eq!(result(&replies, 2), Value::Null);
+    }
+
+    #[test]
+    fn unknown_request() {
+        let replies = exchange(&[request(1, "workspace/nope", json!({}))]);
+        assert_eq!(replies[0]["error"]["code"], json!(METHOD_NOT_FOUND));
+    }
+
+    #[test]
+    fn diagnostics_on_open_and_change() {
+        let replies = exchange(&[
+            open("let x = 5;\nlet = 3;"),
+            notification("textDocument/didChange", json!({
+                "textDocument": { "uri": "file:///test.mk", "version": 2 },
+                "contentChanges": [{ "text": "let x = 5;" }],
+            })),
+            notification("textDocument/didChange", json!({
+                "textDocument": { "uri": "file:///test.mk", "version": 3 },
+                "contentChanges": [{ "text": "let x = 5;\ny;" }],
+            })),
+        ]);
+
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[0]["method"], json!("textDocument/publishDiagnostics"));
+
+        let diagnostics = replies[0]["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["message"], json!("expected an identifier, found `=`"));
+        assert_eq!(diagnostics[0]["severity"], json!(SEVERITY_ERROR));
+        assert_eq!(diagnostics[0]["range"], json!({
+            "start": { "line": 1, "character": 4 },
+            "end": { "line": 1, "character": 5 },
+        }));
+        assert_eq!(diagnostics[0]["relatedInformation"][0]["message"], json!("`let` must be followed by a name"));
+
+        assert_eq!(replies[1]["params"]["diagnostics"], json!([]));
+
+        let diagnostics = replies[2]["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["message"], json!("cannot find `y` in this scope"));
+    }
+
+    #[test]
+    fn document_symbols() {
+        let replies = exchange(&[
+            open("let five = 5;\nlet add = fn(x, y) { x + y; };\nlet other = five;\nadd;"),
+            request(1, "textDocument/documentSymbol", json!({ "textDocument": { "uri": "file:///test.mk" } })),
+        ]);
+
+        let symbols = result(&replies, 1);
+        let summary: Vec<(String, String, u64)> = symbols
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| (
+                s["name"].as_str().unwrap().to_string(),
+                s["detail"].as_str().unwrap().to_string(),
+                s["kind"].as_u64().unwrap(),
+            ))
+            .collect();
+
+        assert_eq!(summary, vec![
+            (String::from("five"), String::from("integer"), SYMBOL_VARIABLE as u64),
+            (String::from("add"), String::from("function"), SYMBOL_FUNCTION as u64),
+            (String::from("other"), String::from("integer"), SYMBOL_VARIABLE as u64),
+        ]);
+        assert_eq!(symbols[1]["selectionRange"], json!({
+            "start": { "line": 1, "character": 4 },
+            "end": { "line": 1, "character": 7 },
+        }));
+    }
+
+    #[test]
+    fn hover_and_definition() {
+        let text = "let flag = true;\nlet x = 1;\nlet x = flag;\nx;\nmissing;\nlater;\nlet later = 1;";
+        let replies = exchange(&[
+            open(text),
+            at(1, "textDocument/hover", 3, 0),
+            at(2, "textDocument/definition", 3, 1),
+            at(3, "textDocument/definition", 2, 10),
+            at(4, "textDocument/hover", 4, 2),
+            at(5, "textDocument/definition", 5, 2),
+        ]);
+
+        assert_eq!(result(&replies, 1)["contents"]["value"], json!("```monkey\nlet x\n```\nboolean"));
+        assert_eq!(result(&replies, 2), json!({
+            "uri": "file:///test.mk",
+            "range": { "start": { "line": 2, "character": 4 }, "end": { "line": 2, "character": 5 } },
+        }));
+        assert_eq!(result(&replies, 3)["range"]["start"], json!({ "line": 0, "character": 4 }));
+        assert_eq!(result(&replies, 4), Value::Null);
+        assert_eq!(result(&replies, 5), Value::Null);
+    }
+
+    #[test]
+    fn completion() {
+        let replies = exchange(&[
+            open("let alpha = 1;\nlet beta = 2;\nlet alpha = 3;"),
+            request(1, "textDocument/completion", json!({
+                "textDocument": { "uri": "file:///test.mk" },
+                "position": { "line": 2, "character": 0 },
+            })),
+        ]);
+
+        let labels: Vec<String> = result(&replies, 1)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap().to_string())
+            .collect();
+
+        let mut expected: Vec<String> = Token::KEYWORDS.iter().map(|k| k.to_string()).collect();
+        expected.push(String::from("alpha"));
+        expected.push(String::from("beta"));
+        assert_eq!(labels, expected);
+    }
+
+    #[test]
+    fn utf16_positions() {
+        let document = Document::new("file:///test.mk", "let s = 1;\n😀 x");
+
+        // the emoji is one char but two UTF-16 code units
+        assert_eq!(document.position(13), json!({ "line": 1, "character": 3 }));
+        assert_eq!(document.offset(&json!({ "line": 1, "character": 3 })), 13);
+        assert_eq!(document.offset(&json!({ "line": 1, "character": 99 })), 14);
+    }
+}
@@ -1,5 +1,182 @@
-use interpreter::repl;
+use std::env;
+use std::fs;
+use std::io::{stdin, stdout, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use interpreter::{diagnostics, export, lint, lsp, repl, resolve, Lexer, Parser, Severity};
+use interpreter::transcript::Transcript;
 
-fn main() {
-    repl::start()
+const USAGE: &str = "\
+usage: interpreter                                   start the REPL
+       interpreter replay <file>...                  replay saved REPL sessions
+       interpreter parse [--format json|sexp] <file> print the syntax tree of a file
+       interpreter lex [--include-trivia] <file>     print the tokens of a file as JSON Lines
+       interpreter lint [--config <file>] <file>...   check files against the lint rules
+       interpreter lsp                               serve the Language Server Protocol over stdio";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(|arg| arg.as_str()) {
+        None => {
+            repl::start();
+            ExitCode::SUCCESS
+        },
+        Some("replay") if args.len() > 1 => replay(&args[1..]),
+        Some("parse") => parse(&args[1..]),
+        Some("lex") => lex(&args[1..]),
+        Some("lint") => lint(&args[1..]),
+        Some("lsp") if args.len() == 1 => match lsp::run(stdin().lock(), stdout().lock()) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("language server failed: {}", e);
+                ExitCode::FAILURE
+            },
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+fn replay(files: &[String]) -> ExitCode {
+    let mut failed = false;
+
+    for file in files {
+        let result = Transcript::load(Path::new(file));
+        match result {
+            Ok(transcript) => match transcript.replay() {
+                Ok(()) => println!("{}: ok", file),
+                Err(mismatch) => {
+                    failed = true;
+                    println!("{}: FAILED\n{}", file, mismatch);
+                },
+            },
+            Err(e) => {
+                failed = true;
+                println!("{}: could not read: {}", file, e);
+            },
+        }
+    }
+
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
+
+fn parse(args: &[String]) -> ExitCode {
+    let (format, file) = match args {
+        [file] => ("json", file),
+        [flag, format, file] if flag == "--format" => (format.as_str(), file),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let source = match read(file) {
+        Some(source) => source,
+        None => return ExitCode::FAILURE,
+    };
+
+    let mut p = Parser::new(Lexer::new(&source));
+    let program = p.parse_program();
+
+    match format {
+        "json" => println!("{:#}", export::to_json(&program)),
+        "sexp" => println!("{}", export::to_sexp(&program)),
+        _ => {
+            eprintln!("unknown format {}, expected json or sexp", format);
+            return ExitCode::FAILURE;
+        },
+    }
+
+    diagnostics::emit(p.errors(), file, &source);
+    if p.errors().is_empty() { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
+
+fn lex(args: &[String]) -> ExitCode {
+    let (include_trivia, file) = match args {
+        [file] => (false, file),
+        [flag, file] if flag == "--include-trivia" => (true, file),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let source = match read(file) {
+        Some(source) => source,
+        None => return ExitCode::FAILURE,
+    };
+
+    let mut out = stdout().lock();
+    for token in export::tokens_json(&source, include_trivia) {
+        // stop quietly when the reader goes away
+        if writeln!(out, "{}", token).is_err() {
+            break;
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn lint(args: &[String]) -> ExitCode {
+    let (config, files) = match args {
+        [flag, config, files @ ..] if flag == "--config" => (Some(config.as_str()), files),
+        files => (None, files),
+    };
+    if files.is_empty() {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    }
+
+    // without --config, use .monkeylint in the working directory if there is one
+    let config = match config {
+        Some(file) => match read(file) {
+            Some(text) => lint::Config::parse(&text).map_err(|e| format!("{}: {}", file, e)),
+            None => return ExitCode::FAILURE,
+        },
+        None => match fs::read_to_string(".monkeylint") {
+            Ok(text) => lint::Config::parse(&text).map_err(|e| format!(".monkeylint: {}", e)),
+            Err(_) => Ok(lint::Config::default()),
+        },
+    };
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("invalid lint config {}", e);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let mut failed = false;
+    for file in files {
+        let Some(source) = read(file) else {
+            failed = true;
+            continue;
+        };
+
+        let mut p = Parser::new(Lexer::new(&source));
+        let program = p.parse_program();
+
+        let mut found = p.errors().to_vec();
+        found.extend(resolve::resolve(&program).diagnostics);
+        found.extend(lint::lint(&source, &program, &config));
+        found.sort_by_key(|d| d.span.start);
+
+        failed |= found.iter().any(|d| d.severity == Severity::Error);
+        diagnostics::emit(&found, file, &source);
+    }
+
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
+
+fn read(file: &str) -> Option<String> {
+    match fs::read_to_string(file) {
+        Ok(source) => Some(source),
+        Err(e) => {
+            eprintln!("could not read {}: {}", file, e);
+            None
+        },
+    }
 }
@@ -1,13 +1,14 @@
-use crate::tokens::Token;
+use crate::tokens::{Token, Span};
 use crate::lexer::Lexer;
-use crate::ast::{Statement, Program};
+use crate::ast::{Statement, LetStatement, ReturnStatement, ExpressionStatement, Program, TypeAnnotation};
 use std::collections::HashMap;
 use crate::expressions::Expression;
+use crate::diagnostics::Diagnostic;
 
-type PrefixFn = fn() -> Expression;
-type InfixFn = fn(Expression) -> Expression;
+type PrefixFn = fn(&mut Parser) -> Option<Expression>;
+type InfixFn = fn(&mut Parser, Expression) -> Option<Expression>;
 
-pub enum Precedence {
+enum Precedence {
     LOWEST,
     EQUALS,
     LESSGREATER,
@@ -31,46 +32,71 @@ impl Precedence {
     }
 }
 
-struct Parser {
+pub struct Parser {
     lexer: Lexer,
     cur_token: Token,
+    cur_span: Span,
+    prev_span: Span,
     peek_token: Token,
-    prefix_parse_fns: HashMap<String, fn(&Token)->Token>,
-    // infix_parse_fns: HashMap<String, fn()>,
+    peek_span: Span,
+    prefix_parse_fns: HashMap<String, PrefixFn>,
+    // infix_parse_fns: HashMap<String, InfixFn>,
+    errors: Vec<Diagnostic>,
 }
 
 impl Parser {
-    fn new(mut l: Lexer) -> Parser {
-        let cur_token = l.next_token();
-        let peek_token = l.next_token();
+    pub fn new(mut l: Lexer) -> Parser {
+        let (cur_token, cur_span) = l.next_spanned_token();
+        let (peek_token, peek_span) = l.next_spanned_token();
 
-        let prefix_parse_fns = HashMap::new();
+        let mut prefix_parse_fns: HashMap<String, PrefixFn> = HashMap::new();
         // let infix_parse_fns = HashMap::new();
 
-        prefix_parse_fns.insert("identifier".to_string(), parse_identifier);
-
-        Parser { lexer: l, cur_token, peek_token, prefix_parse_fns }
+        prefix_parse_fns.insert("identifier".to_string(), Parser::parse_identifier);
+
+        Parser {
+            lexer: l,
+            cur_token,
+            cur_span,
+            prev_span: Span::default(),
+            peek_token,
+            peek_span,
+            prefix_parse_fns,
+            errors: vec![],
+        }
     } 
 
     fn next_token(&mut self) {
+        self.prev_span = self.cur_span;
         self.cur_token = self.peek_token.clone();
-        self.peek_token = self.lexer.next_token();
+        self.cur_span = self.peek_span;
+        (self.peek_token, self.peek_span) = self.lexer.next_spanned_token();
+    }
+
+    /// Lexer and parser diagnostics, ordered by where they occur in the input.
+    pub fn errors(&self) -> &[Diagnostic] {
+        &self.errors
     }
 
-    fn parse_program(mut self) -> Program {
+    pub fn parse_program(&mut self) -> Program {
         let mut statements: Vec<Statement> = vec![];
 
         while self.cur_token != Token::Eof {
-            let statement = self.parse_statement();
-
-            statements.push(statement);
+            match self.parse_statement() {
+                Some(statement) => statements.push(statement),
+                // recover at the end of the broken statement to avoid cascading errors
+                None => self.skip_to_semicolon(),
+            }
             self.next_token();
         }
 
+        self.errors.extend_from_slice(self.lexer.errors());
+        self.errors.sort_by_key(|e| e.span.start);
+
         Program { statements }
     }
 
-    fn parse_statement(&mut self) -> Statement {
+    fn parse_statement(&mut self) -> Option<Statement> {
         match self.cur_token {
             Token::Let => self.parse_let_statement(),
             Token::Return => self.parse_return_statement(),
@@ -78,72 +104,149 @@ impl Parser {
         }
     }
 
-    fn parse_let_statement(&mut self) -> Statement {
+    fn parse_let_statement(&mut self) -> Option<Statement> {
         let token = self.cur_token.clone();
+        let let_span = self.cur_span;
 
         match self.peek_token {
             Token::Ident(_) => self.next_token(),
-            _ => panic!("Next token not Ident")
+            _ => {
+                self.peek_error("an identifier", Some((let_span, "`let` must be followed by a name")));
+                return None;
+            }
         }
 
         let identifier = self.cur_token.clone();
+        let identifier_span = self.cur_span;
+
+        let mut annotation = None;
+        if self.peek_token == Token::Colon {
+            self.next_token();
+            let colon_span = self.cur_span;
+
+            match self.peek_token {
+                Token::Ident(_) => self.next_token(),
+                _ => {
+                    self.peek_error("a type name", Some((colon_span, "`:` must be followed by a type")));
+                    return None;
+                }
+            }
+            annotation = Some(TypeAnnotation { token: self.cur_token.clone(), span: self.cur_span });
+        }
 
         match self.peek_token {
             Token::Assign => self.next_token(),
-            _ => panic!("Next token not Ident")
+            _ => {
+                self.peek_error("`=`", None);
+                return None;
+            }
         }
 
         // TODO: parse expression
 
-        loop {
-            match self.cur_token {
-                Token::SemiColon => break,
-                _ => self.next_token(),
-            }
-        }
+        self.skip_to_semicolon();
 
-        Statement::Let { token, identifier }
+        let span = let_span.to(self.statement_end());
+        Some(Statement::Let(LetStatement { token, identifier, identifier_span, annotation, span }))
     }
 
-    fn parse_return_statement(&mut self) -> Statement {
+    fn parse_return_statement(&mut self) -> Option<Statement> {
         let token = self.cur_token.clone();
+        let return_span = self.cur_span;
 
         self.next_token();
 
         // TODO: parse expression
 
-        loop {
-            match self.cur_token {
-                Token::SemiColon => break,
-                _ => self.next_token(),
-            }
-        }
+        self.skip_to_semicolon();
 
-        Statement::Return { token }
+        let span = return_span.to(self.statement_end());
+        Some(Statement::Return(ReturnStatement { token, span }))
     }
 
-    fn parse_expression_statement(&mut self) -> Statement {
+    fn parse_expression_statement(&mut self) -> Option<Statement> {
         let token = self.cur_token.clone();
-        let expression = self.parse_expression(Precedence::LOWEST);
+        let start = self.cur_span;
+        let expression = self.parse_expression(Precedence::LOWEST)?;
+
+        if self.peek_token == Token::SemiColon {
+            self.next_token();
+        }
+
+        let span = start.to(self.statement_end());
+        Some(Statement::Expression(ExpressionStatement { token, expression, span }))
+    }
+
+    fn parse_expression(&mut self, _precedence: Precedence) -> Option<Expression> {
+        let token_name = Token::name(&self.cur_token);
 
+        match self.prefix_parse_fns.get(&token_name).copied() {
+            Some(prefix) => prefix(self),
+            None => {
+                self.no_prefix_parse_fn_error();
+                None
+            }
+        }
+    }
+
+    fn parse_identifier(&mut self) -> Option<Expression> {
         match self.cur_token {
-            Token::SemiColon => self.next_token(),
-            _ => {},
+            Token::Ident(_) => Some(Expression::Identifier {
+                token: self.cur_token.clone(),
+                span: self.cur_span,
+            }),
+            _ => None,
         }
+    }
 
-        Statement::Expression { token, expression }
+    /// Span of the last token of the statement ending at the current token.
+    fn statement_end(&self) -> Span {
+        match self.cur_token {
+            Token::Eof => self.prev_span,
+            _ => self.cur_span,
+        }
     }
 
-    fn parse_expression(&self, precedence: Precedence) -> Expression {
-        let token_name = Token::name(&self.cur_token)
-        self.prefix_parse_fns.get(&self.cur_token).unwrap()()
+    /// Advances to the `;` that ends the statement, stepping over any `;`
+    /// nested in `{}` or `()`, as in function bodies.
+    fn skip_to_semicolon(&mut self) {
+        let mut depth = 0usize;
+
+        while self.cur_token != Token::Eof && !(depth == 0 && self.cur_token == Token::SemiColon) {
+            match self.cur_token {
+                Token::LBrace | Token::LParen => depth += 1,
+                Token::RBrace | Token::RParen => depth = depth.saturating_sub(1),
+                _ => {},
+            }
+            self.next_token();
+        }
     }
-}
 
-fn parse_identifier(cur_token: &Token) -> Token {
-    match cur_token {
-        Token::Ident(_) => cur_token.clone(),
-        _ => panic!("Token is not identifier, got {:?}", cur_token),
+    fn peek_error(&mut self, expected: &str, secondary: Option<(Span, &str)>) {
+        let mut diagnostic = Diagnostic::error(
+            format!("expected {}, found {}", expected, self.peek_token.describe()),
+            self.peek_span,
+        ).with_label(format!("expected {}", expected));
+
+        if let Some((span, message)) = secondary {
+            diagnostic = diagnostic.with_secondary(span, message);
+        }
+
+        self.errors.push(diagnostic);
+    }
+
+    fn no_prefix_parse_fn_error(&mut self) {
+        // the lexer already reported illegal characters
+        if let Token::Illegal(_) = self.cur_token {
+            return;
+        }
+
+        self.errors.push(
+            Diagnostic::error(
+                format!("expected an expression, found {}", self.cur_token.describe()),
+                self.cur_span,
+            ).with_label("expected an expression")
+        );
     }
 }
 
@@ -152,6 +255,20 @@ mod test {
     use crate::ast::Node;
     use super::*;
 
+    fn check_parser_errors(p: &Parser, input: &str) {
+        let errors = p.errors();
+        if errors.is_empty() {
+            return;
+        }
+
+        let rendered = errors
+            .iter()
+            .map(|e| e.render("test", input, false))
+            .collect::<Vec<String>>()
+            .join("\n");
+        panic!("Parser has {} errors\n{}", errors.len(), rendered);
+    }
+
     #[test]
     fn let_statement() {
         let input = String::from("\
@@ -160,13 +277,14 @@ let y = 10;
 let foobar = 838383;");
 
         let l = Lexer::new(&input);
-        let p = Parser::new(l);
+        let mut p = Parser::new(l);
         let program = p.parse_program();
+        check_parser_errors(&p, &input);
 
         let n = program.statements.len();
         assert_eq!(n, 3, "Let statement must have 3 elements, got {}", n);
 
-        let expected_identifiers = vec![
+        let expected_identifiers = [
             String::from("x"),
             String::from("y"),
             String::from("foobar"),
@@ -181,11 +299,74 @@ let foobar = 838383;");
             let token_literal = statement.token_literal();
             assert_eq!(token_literal, String::from("let"), "Token literal not \"let\" got {}", token_literal);
 
-            let name = &statement.name_token_literal();
-            assert_eq!(expected, name, "Expected token {}, got {}", expected, name);
+            let name = statement.name_token_literal();
+            assert_eq!(Some(expected), name.as_ref(), "Expected token {}, got {:?}", expected, name);
         }
     }
 
+    #[test]
+    fn semicolons_inside_braces() {
+        let input = String::from("\
+let add = fn(x, y) {
+    x + y;
+};
+let result = add(five, ten);
+return fn() { return 1; };
+result;");
+
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(&p, &input);
+
+        let spans: Vec<Span> = program.statements.iter().map(|s| s.span()).collect();
+        assert_eq!(spans, vec![Span::new(0, 34), Span::new(35, 63), Span::new(64, 90), Span::new(91, 98)]);
+    }
+
+    #[test]
+    fn annotated_let_statement() {
+        let input = String::from("\
+let x: int = 5;
+let y = 10;");
+
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(&p, &input);
+
+        let annotations: Vec<Option<TypeAnnotation>> = program.statements
+            .iter()
+            .map(|statement| match statement {
+                Statement::Let(s) => s.annotation.clone(),
+                s => panic!("Statement not let. Got {:?}", s),
+            })
+            .collect();
+
+        assert_eq!(annotations, vec![
+            Some(TypeAnnotation { token: Token::Ident(String::from("int")), span: Span::new(7, 10) }),
+            None,
+        ]);
+        assert_eq!(program.statements[0].span(), Span::new(0, 15));
+    }
+
+    #[test]
+    fn annotation_errors() {
+        let input = String::from("\
+let x: = 5;
+let y: int 5;");
+
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        p.parse_program();
+
+        let messages: Vec<(&str, Span)> = p.errors().iter().map(|e| (e.message.as_str(), e.span)).collect();
+        assert_eq!(messages, vec![
+            ("expected a type name, found `=`", Span::new(7, 8)),
+            ("expected `=`, found integer `5`", Span::new(23, 24)),
+        ]);
+        assert_eq!(p.errors()[0].secondary[0].span, Span::new(5, 6));
+    }
+
     #[test]
     fn return_statement() {
         let input = String::from("\
@@ -194,8 +375,9 @@ return 10;
 return 3301;");
 
         let l = Lexer::new(&input);
-        let p = Parser::new(l);
+        let mut p = Parser::new(l);
         let program = p.parse_program();
+        check_parser_errors(&p, &input);
 
         let n = program.statements.len();
         assert_eq!(n, 3, "Return statements must have 3 elements. Got {}", n);
@@ -203,6 +385,7 @@ return 3301;");
         for statement in program.statements {
             let token_literal = statement.token_literal();
             assert_eq!(token_literal, String::from("return"), "Token literal not \"let\" got {}", token_literal);
+            assert_eq!(statement.name_token_literal(), None);
         }
     }
 
@@ -212,8 +395,9 @@ return 3301;");
 foobar;");
 
         let l = Lexer::new(&input);
-        let p = Parser::new(l);
+        let mut p = Parser::new(l);
         let program = p.parse_program();
+        check_parser_errors(&p, &input);
 
         let n = program.statements.len();
         assert_eq!(n, 1, "Program has not enough statements. Got {}", n);
@@ -221,18 +405,78 @@ foobar;");
 
         let statement = &program.statements[0];
 
-        // match statement {
-        //     Statement::Expression { expression, .. } => {
-        //         match expression {
-        //             Token::Ident(i) => {
-        //                 if i != &String::from("foobar") {
-        //                     panic!("Identifier value not correct. Got {:?}", i)
-        //                 }
-        //             },
-        //             _ => panic!("Expression not identifier")
-        //         }
-        //     },
-        //     s => panic!("Statement not expression. Got {:?}", s),
-        // };
+        match statement {
+            Statement::Expression(s) => {
+                match &s.expression {
+                    Expression::Identifier { token: Token::Ident(i), .. } => {
+                        if i != &String::from("foobar") {
+                            panic!("Identifier value not correct. Got {:?}", i)
+                        }
+                    },
+                    e => panic!("Expression not identifier. Got {:?}", e)
+                }
+            },
+            s => panic!("Statement not expression. Got {:?}", s),
+        };
+    }
+
+    #[test]
+    fn comments() {
+        let input = String::from("\
+// a comment @ with ; anything in it
+let x = 5; // let y = 10;
+// x;
+foobar; // trailing");
+
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(&p, &input);
+
+        let statements: Vec<String> = program.statements.iter().map(|s| s.string()).collect();
+        assert_eq!(statements, vec!["let x = ;", "foobar"]);
+        assert_eq!(program.statements[1].span(), Span::new(69, 76));
+    }
+
+    #[test]
+    fn let_statement_errors() {
+        let input = String::from("\
+let x 5;
+let = 10;
+let 838383;");
+
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        p.parse_program();
+
+        let expected = [
+            ("expected `=`, found integer `5`", Span::new(6, 7)),
+            ("expected an identifier, found `=`", Span::new(13, 14)),
+            ("expected an identifier, found integer `838383`", Span::new(23, 29)),
+        ];
+
+        let errors = p.errors();
+
+        let n = errors.len();
+        assert_eq!(n, expected.len(), "Parser must report {} errors, got {}", expected.len(), n);
+
+        for (expected, error) in expected.iter().zip(errors.iter()) {
+            assert_eq!(expected.0, error.message);
+            assert_eq!(expected.1, error.span);
+        }
+    }
+
+    #[test]
+    fn illegal_character_error() {
+        let input = String::from("let x = 5 @ 3;");
+
+        let l = Lexer::new(&input);
+        let mut p = Parser::new(l);
+        p.parse_program();
+
+        let errors = p.errors();
+        assert_eq!(errors.len(), 1, "Expected one error, got {:?}", errors);
+        assert_eq!(errors[0].message, "illegal character `@`");
+        assert_eq!(errors[0].span, Span::new(10, 11));
     }
 }
@@ -1,33 +1,421 @@
-use std::io::{stdin, stdout, Write};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, stdout, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Instant;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::{CmdKind, Highlighter};
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
 use crate::tokens::Token;
 use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::ast::{Statement, LetStatement};
+use crate::highlight;
+use crate::transcript::{Entry, Transcript};
+
+pub const PROMPT: &str = "|monkey> ";
+
+const COMMANDS: [(&str, &str); 6] = [
+    (":load <file>", "run a Monkey file in the session"),
+    (":env", "list the names bound in the session"),
+    (":reset", "forget every binding"),
+    (":time <input>", "run the input and report how long it took"),
+    (":save <file>", "save the session as a transcript to replay later"),
+    (":help", "show this message"),
+];
 
 pub fn start() {
     println!("Hello! this is the Monkney programming language!");
-    println!("Feel free to type in commands\n");
+    println!("Feel free to type in commands, :help lists the REPL commands\n");
 
-    let prefix = String::from("|monkey> ");
-    let mut input = String::new();
+    let session = Rc::new(RefCell::new(Session::new(stdout().is_terminal())));
+
+    let mut editor: Editor<MonkeyHelper, DefaultHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("Could not start the line editor: {}", e);
+            return
+        }
+    };
+    editor.set_helper(Some(MonkeyHelper::new(Rc::clone(&session))));
+
+    let history = history_path();
+    if let Some(path) = &history {
+        let _ = editor.load_history(path);
+    }
 
     loop {
-        print!("{}", prefix);
-        let _ = stdout().flush();
-        let _ = stdin().read_line(&mut input);
+        let input = match editor.readline(PROMPT) {
+            Ok(line) => line,
+            // Ctrl-C clears the line, Ctrl-D leaves
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("Could not read input: {}", e);
+                break
+            }
+        };
 
-        if input == String::from("exit\n") {
+        if input == "exit" {
             break
         }
 
-        let mut l = Lexer::new(&input);
+        if !input.trim().is_empty() {
+            let _ = editor.add_history_entry(input.as_str());
+        }
+
+        let _ = session.borrow_mut().run(&input, &mut stdout().lock());
+    }
+
+    if let Some(path) = &history {
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = editor.save_history(path);
+    }
+    println!("Bye!");
+}
+
+/// State kept between the inputs of a REPL session.
+pub struct Session {
+    names: BTreeSet<String>,
+    color: bool,
+    transcript: Transcript,
+}
+
+impl Session {
+    pub fn new(color: bool) -> Self {
+        Session { names: BTreeSet::new(), color, transcript: Transcript::default() }
+    }
+
+    /// Every input so far and what it printed, without colors.
+    pub fn transcript(&self) -> &Transcript {
+        &self.transcript
+    }
+
+    /// Runs one line typed at the prompt, either a `:` command or Monkey source.
+    pub fn run(&mut self, input: &str, out: &mut impl Write) -> io::Result<()> {
+        let mut buffer = vec![];
+        self.dispatch(input, &mut buffer)?;
+
+        if !input.trim().starts_with(":save") {
+            let output = String::from_utf8_lossy(&buffer);
+            self.transcript.entries.push(Entry {
+                input: input.to_string(),
+                output: strip_colors(&output),
+            });
+        }
+
+        out.write_all(&buffer)
+    }
+
+    fn dispatch(&mut self, input: &str, out: &mut impl Write) -> io::Result<()> {
+        let trimmed = input.trim();
+
+        if !trimmed.starts_with(':') {
+            return self.eval(input, "<repl>", out);
+        }
+
+        let (command, argument) = match trimmed.split_once(char::is_whitespace) {
+            Some((command, argument)) => (command, argument.trim()),
+            None => (trimmed, ""),
+        };
+
+        match command {
+            ":load" => {
+                if argument.is_empty() {
+                    return writeln!(out, "usage: :load <file>");
+                }
+                match fs::read_to_string(argument) {
+                    Ok(source) => self.eval(&source, argument, out),
+                    Err(e) => writeln!(out, "could not read {}: {}", argument, e),
+                }
+            },
+            ":env" => {
+                for name in &self.names {
+                    writeln!(out, "{}", name)?;
+                }
+                Ok(())
+            },
+            ":reset" => {
+                self.names.clear();
+                Ok(())
+            },
+            ":time" => {
+                let start = Instant::now();
+                self.eval(argument, "<repl>", out)?;
+                writeln!(out, "time: {:?}", start.elapsed())
+            },
+            ":save" => {
+                if argument.is_empty() {
+                    return writeln!(out, "usage: :save <file>");
+                }
+                match self.transcript.save(Path::new(argument)) {
+                    Ok(()) => writeln!(out, "saved {} inputs to {}", self.transcript.entries.len(), argument),
+                    Err(e) => writeln!(out, "could not write {}: {}", argument, e),
+                }
+            },
+            ":help" => {
+                for (usage, description) in COMMANDS {
+                    writeln!(out, "{:<16}{}", usage, description)?;
+                }
+                writeln!(out, "{:<16}leave the REPL", "exit")
+            },
+            _ => writeln!(out, "unknown command {}, :help lists the commands", command),
+        }
+    }
+
+    fn eval(&mut self, source: &str, file: &str, out: &mut impl Write) -> io::Result<()> {
+        let mut l = Lexer::new(source);
         loop {
             let tok = l.next_token();
             match tok {
                 Token::Eof => break,
-                _ => println!("{:?}", tok)
+                Token::Illegal(_) => {},
+                _ => writeln!(out, "{:?}", tok)?,
             }
         }
 
-        input.clear();
+        for diagnostic in l.errors() {
+            writeln!(out, "{}", diagnostic.render(file, source, self.color))?;
+        }
+
+        self.bind(source);
+        Ok(())
+    }
+
+    fn bind(&mut self, source: &str) {
+        let mut p = Parser::new(Lexer::new(source));
+
+        for statement in p.parse_program().statements {
+            if let Statement::Let(LetStatement { identifier: Token::Ident(name), .. }) = statement {
+                self.names.insert(name);
+            }
+        }
+    }
+}
+
+fn strip_colors(text: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // skip the escape up to its final `m`
+            for c in chars.by_ref() {
+                if c == 'm' {
+                    break;
+                }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+
+    plain
+}
+
+fn history_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("monkey").join("history"))
+}
+
+/// Completes keywords and the names bound with `let` during the session,
+/// and highlights the input as it is typed.
+struct MonkeyHelper {
+    session: Rc<RefCell<Session>>,
+    // brackets are not highlighted on the final refresh of a line
+    brackets: Cell<bool>,
+}
+
+impl MonkeyHelper {
+    fn new(session: Rc<RefCell<Session>>) -> Self {
+        MonkeyHelper { session, brackets: Cell::new(false) }
+    }
+}
+
+impl Completer for MonkeyHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| Lexer::is_letter(&c.to_string()))
+            .last()
+            .map(|(i, _)| i)
+            .unwrap_or(pos);
+        let word = &line[start..pos];
+
+        if word.is_empty() {
+            return Ok((pos, vec![]));
+        }
+
+        let session = self.session.borrow();
+        let candidates: BTreeSet<&str> = Token::KEYWORDS
+            .iter()
+            .copied()
+            .chain(session.names.iter().map(|name| name.as_str()))
+            .filter(|candidate| candidate.starts_with(word))
+            .collect();
+
+        let pairs = candidates
+            .into_iter()
+            .map(|candidate| Pair { display: candidate.to_string(), replacement: candidate.to_string() })
+            .collect();
+
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for MonkeyHelper {
+    type Hint = String;
+}
+
+impl Highlighter for MonkeyHelper {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        let cursor = if self.brackets.get() { Some(pos) } else { None };
+        Cow::Owned(highlight::highlight(line, cursor))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, kind: CmdKind) -> bool {
+        self.brackets.set(kind != CmdKind::ForcedRefresh);
+        true
+    }
+}
+
+impl Validator for MonkeyHelper {}
+
+impl Helper for MonkeyHelper {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diagnostics::Diagnostic;
+    use crate::tokens::Span;
+
+    fn complete(helper: &MonkeyHelper, line: &str) -> (usize, Vec<String>) {
+        let history = DefaultHistory::new();
+        let ctx = Context::new(&history);
+        let (start, pairs) = helper.complete(line, line.len(), &ctx).unwrap();
+
+        (start, pairs.into_iter().map(|p| p.replacement).collect())
+    }
+
+    fn helper() -> MonkeyHelper {
+        MonkeyHelper::new(Rc::new(RefCell::new(Session::new(false))))
+    }
+
+    fn run(session: &mut Session, input: &str) -> String {
+        let mut out = vec![];
+        session.run(input, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn complete_keywords() {
+        let helper = helper();
+
+        assert_eq!(complete(&helper, "l"), (0, vec![String::from("let")]));
+        assert_eq!(complete(&helper, "let x = f"), (8, vec![String::from("false"), String::from("fn")]));
+        assert_eq!(complete(&helper, "let x = "), (8, vec![]));
+    }
+
+    #[test]
+    fn complete_bound_names() {
+        let helper = helper();
+        run(&mut helper.session.borrow_mut(), "let foobar = 5; let fizz = 10;");
+        run(&mut helper.session.borrow_mut(), "let fizzbuzz = 15;");
+
+        let expected = vec![String::from("fizz"), String::from("fizzbuzz")];
+        assert_eq!(complete(&helper, "fi"), (0, expected));
+        assert_eq!(complete(&helper, "add(foo"), (4, vec![String::from("foobar")]));
+    }
+
+    #[test]
+    fn eval_prints_tokens_and_diagnostics() {
+        let mut session = Session::new(false);
+
+        let expected = "\
+Let
+Ident(\"x\")
+Assign
+Int(5)
+SemiColon
+error: illegal character `@`
+ --> <repl>:1:11
+  |
+1 | let x = 5 @;
+  |           ^ not a valid Monkey token
+
+";
+        assert_eq!(run(&mut session, "let x = 5 @;"), expected);
+    }
+
+    #[test]
+    fn env_and_reset() {
+        let mut session = Session::new(false);
+        run(&mut session, "let b = 1;");
+        run(&mut session, "let a = 2;");
+
+        assert_eq!(run(&mut session, ":env"), "a\nb\n");
+        assert_eq!(run(&mut session, ":reset"), "");
+        assert_eq!(run(&mut session, ":env"), "");
+    }
+
+    #[test]
+    fn load() {
+        let path = std::env::temp_dir().join(format!("monkey-load-{}.mk", std::process::id()));
+        fs::write(&path, "let loaded = 1;\n@").unwrap();
+
+        let mut session = Session::new(false);
+        let output = run(&mut session, &format!(":load {}", path.display()));
+        fs::remove_file(&path).unwrap();
+
+        assert!(output.starts_with("Let\nIdent(\"loaded\")\n"), "Unexpected output {}", output);
+        assert!(output.contains(&format!(" --> {}:2:1", path.display())), "Unexpected output {}", output);
+        assert_eq!(run(&mut session, ":env"), "loaded\n");
+
+        let output = run(&mut session, ":load does/not/exist.mk");
+        assert!(output.starts_with("could not read does/not/exist.mk"), "Unexpected output {}", output);
+    }
+
+    #[test]
+    fn time() {
+        let mut session = Session::new(false);
+        let output = run(&mut session, ":time let t = 1;");
+
+        assert!(output.starts_with("Let\nIdent(\"t\")\n"), "Unexpected output {}", output);
+        assert!(output.lines().last().unwrap().starts_with("time: "), "Unexpected output {}", output);
+    }
+
+    #[test]
+    fn records_without_colors() {
+        let mut session = Session::new(true);
+        run(&mut session, "@");
+        run(&mut session, ":save");
+
+        let entries = &session.transcript().entries;
+        assert_eq!(entries.len(), 1, "Only `@` should be recorded, got {:?}", entries);
+        assert_eq!(entries[0].output, Diagnostic::error("illegal character `@`", Span::new(0, 1))
+            .with_label("not a valid Monkey token")
+            .render("<repl>", "@", false) + "\n");
+    }
+
+    #[test]
+    fn help_and_unknown_commands() {
+        let mut session = Session::new(false);
+        let help = run(&mut session, ":help");
+
+        for (usage, _) in COMMANDS {
+            assert!(help.contains(usage), "Help does not mention {}", usage);
+        }
+        assert_eq!(run(&mut session, ":nope"), "unknown command :nope, :help lists the commands\n");
     }
-    println!("Bye!");
 }
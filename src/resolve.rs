@@ -0,0 +1,176 @@
+use crate::tokens::{Token, Span};
+use crate::ast::{Program, LetStatement, Visitor};
+use crate::diagnostics::Diagnostic;
+
+/// An identifier use and the `let` name it resolved to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Reference {
+    pub span: Span,
+    pub declaration: Span,
+}
+
+/// The result of name resolution: every use that found its binding, and
+/// the problems found along the way, in source order.
+#[derive(Debug, Default)]
+pub struct Resolution {
+    pub references: Vec<Reference>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Binds every identifier use in `program` to the `let` that declares it.
+///
+/// Reports uses with no binding, uses before the `let` they refer to and
+/// bindings that shadow an earlier one.
+///
+/// The parser does not keep `let` and `return` values yet, so uses inside
+/// them are not seen. For the same reason there are no unused binding
+/// warnings: a name read only in a value would be reported by mistake.
+/// Functions are not parsed either, so there is one scope and no parameters.
+pub fn resolve(program: &Program) -> Resolution {
+    let mut declarations = Declarations { bindings: vec![] };
+    declarations.visit_program(program);
+
+    let mut resolver = Resolver {
+        declarations: declarations.bindings,
+        scope: vec![],
+        resolution: Resolution::default(),
+    };
+    resolver.visit_program(program);
+    resolver.finish()
+}
+
+struct Binding {
+    name: String,
+    span: Span,
+}
+
+/// First pass: every `let` in the program, to tell a read before
+/// initialization from an undefined name.
+struct Declarations {
+    bindings: Vec<Binding>,
+}
+
+impl Visitor for Declarations {
+    fn visit_let_statement(&mut self, statement: &LetStatement) {
+        self.bindings.push(Binding { name: statement.identifier.string(), span: statement.identifier_span });
+    }
+}
+
+struct Resolver {
+    declarations: Vec<Binding>,
+    // bindings in declaration order; lookups take the last one with a name
+    scope: Vec<Binding>,
+    resolution: Resolution,
+}
+
+impl Resolver {
+    fn finish(mut self) -> Resolution {
+        self.resolution.diagnostics.sort_by_key(|d| d.span.start);
+        self.resolution
+    }
+}
+
+impl Visitor for Resolver {
+    fn visit_let_statement(&mut self, statement: &LetStatement) {
+        let name = statement.identifier.string();
+        let identifier_span = statement.identifier_span;
+
+        if let Some(earlier) = self.scope.iter().rev().find(|b| b.name == name) {
+            self.resolution.diagnostics.push(
+                Diagnostic::warning(format!("`{}` shadows an earlier binding", name), identifier_span)
+                    .with_label("shadows the earlier binding")
+                    .with_secondary(earlier.span, "first bound here"),
+            );
+        }
+
+        self.scope.push(Binding { name, span: identifier_span });
+    }
+
+    fn visit_identifier(&mut self, token: &Token, span: Span) {
+        let name = token.string();
+
+        if let Some(binding) = self.scope.iter().rev().find(|b| b.name == name) {
+            self.resolution.references.push(Reference { span, declaration: binding.span });
+            return;
+        }
+
+        let diagnostic = match self.declarations.iter().find(|b| b.name == name) {
+            Some(later) => Diagnostic::error(format!("`{}` is used before it is defined", name), span)
+                .with_label("used here")
+                .with_secondary(later.span, "defined here"),
+            None => Diagnostic::error(format!("cannot find `{}` in this scope", name), span)
+                .with_label("not defined"),
+        };
+        self.resolution.diagnostics.push(diagnostic);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::diagnostics::Severity;
+
+    fn resolve_source(input: &str) -> Resolution {
+        let mut p = Parser::new(Lexer::new(input));
+        let program = p.parse_program();
+        assert!(p.errors().is_empty(), "Parser errors {:?}", p.errors());
+        resolve(&program)
+    }
+
+    fn messages(resolution: &Resolution) -> Vec<(Severity, String)> {
+        resolution.diagnostics.iter().map(|d| (d.severity, d.message.clone())).collect()
+    }
+
+    #[test]
+    fn references() {
+        let resolution = resolve_source("let x = 5;\nlet y = 1;\nx;\ny;\nx;");
+
+        assert_eq!(resolution.references, vec![
+            Reference { span: Span::new(22, 23), declaration: Span::new(4, 5) },
+            Reference { span: Span::new(25, 26), declaration: Span::new(15, 16) },
+            Reference { span: Span::new(28, 29), declaration: Span::new(4, 5) },
+        ]);
+        assert!(resolution.diagnostics.is_empty(), "Unexpected diagnostics {:?}", resolution.diagnostics);
+    }
+
+    #[test]
+    fn undefined_and_before_definition() {
+        let resolution = resolve_source("foo;\nx;\nlet x = 5;\nx;");
+
+        assert_eq!(messages(&resolution), vec![
+            (Severity::Error, String::from("cannot find `foo` in this scope")),
+            (Severity::Error, String::from("`x` is used before it is defined")),
+        ]);
+
+        let early = &resolution.diagnostics[1];
+        assert_eq!(early.span, Span::new(5, 6));
+        assert_eq!(early.secondary[0].span, Span::new(12, 13));
+        assert_eq!(resolution.references, vec![Reference { span: Span::new(19, 20), declaration: Span::new(12, 13) }]);
+    }
+
+    #[test]
+    fn shadowing() {
+        let resolution = resolve_source("let x = 1;\nlet x = 2;\nx;");
+
+        assert_eq!(messages(&resolution), vec![
+            (Severity::Warning, String::from("`x` shadows an earlier binding")),
+        ]);
+
+        let shadow = &resolution.diagnostics[0];
+        assert_eq!(shadow.span, Span::new(15, 16));
+        assert_eq!(shadow.secondary[0].span, Span::new(4, 5));
+    }
+
+    #[test]
+    fn bindings_read_in_values() {
+        // values are not in the AST, so these reads are invisible and must not warn
+        let inputs = ["let x = 5; let y = x; y;", "let x = 5; return x;", "let unused = 3;"];
+
+        for input in inputs {
+            let resolution = resolve_source(input);
+            assert!(resolution.diagnostics.is_empty(), "Unexpected diagnostics for {:?}: {:?}", input, resolution.diagnostics);
+        }
+    }
+}
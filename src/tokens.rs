@@ -20,6 +20,8 @@ pub enum Token {
     // delimiters
     Comma,
     SemiColon,
+    Colon,
+    Arrow,
     LParen,
     RParen,
     LBrace,
@@ -38,6 +40,9 @@ pub enum Token {
 }
 
 impl Token {
+    /// Every word `lookup_ident` turns into a keyword token.
+    pub const KEYWORDS: [&'static str; 7] = ["fn", "let", "true", "false", "if", "else", "return"];
+
     pub fn lookup_ident(string: &String) -> Token {
         let literal = string.to_string();
         match string.as_str() {
@@ -68,6 +73,8 @@ impl Token {
             Token::Gt => Self::name(&Token::Gt),
             Token::Comma => Self::name(&Token::Comma),
             Token::SemiColon => Self::name(&Token::SemiColon),
+            Token::Colon => Self::name(&Token::Colon),
+            Token::Arrow => Self::name(&Token::Arrow),
             Token::LParen => Self::name(&Token::LParen),
             Token::RParen => Self::name(&Token::RParen),
             Token::LBrace => Self::name(&Token::LBrace),
@@ -86,9 +93,9 @@ impl Token {
     
     pub fn name(token: &Token) -> String {
         match token {
-            Token::Illegal(s) => "illegal".to_string(),
-            Token::Ident(s) => "identifier".to_string(),
-            Token::Int(s) => "integer".to_string(),
+            Token::Illegal(_) => "illegal".to_string(),
+            Token::Ident(_) => "identifier".to_string(),
+            Token::Int(_) => "integer".to_string(),
             Token::Eof => "".to_string(),
             Token::Assign => "=".to_string(),
             Token::Plus => "+".to_string(),
@@ -100,8 +107,10 @@ impl Token {
             Token::Gt => ">".to_string(),
             Token::Comma => ",".to_string(),
             Token::SemiColon => ";".to_string(),
+            Token::Colon => ":".to_string(),
+            Token::Arrow => "->".to_string(),
             Token::LParen => "(".to_string(),
-            Token::RParen => "".to_string(),
+            Token::RParen => ")".to_string(),
             Token::LBrace => "{".to_string(),
             Token::RBrace => "}".to_string(),
             Token::Function => "fn".to_string(),
@@ -115,4 +124,60 @@ impl Token {
             Token::NotEq => "!=".to_string(),
         }
     }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Token::Eof => "end of input".to_string(),
+            Token::Illegal(s) => format!("illegal character `{}`", s),
+            Token::Ident(s) => format!("identifier `{}`", s),
+            Token::Int(i) => format!("integer `{}`", i),
+            _ => format!("`{}`", self.string()),
+        }
+    }
+}
+
+/// Range of characters `[start, end)` a token covers in the lexer input.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    pub fn to(&self, other: Span) -> Span {
+        Span { start: self.start.min(other.start), end: self.end.max(other.end) }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum TriviaKind {
+    Whitespace,
+    // `//` up to, not including, the end of the line
+    Comment,
+}
+
+/// Source text between tokens that the parser never sees.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keywords() {
+        for keyword in Token::KEYWORDS {
+            let token = Token::lookup_ident(&keyword.to_string());
+            assert!(!matches!(token, Token::Ident(_)), "{} is not a keyword", keyword);
+            assert_eq!(token.string(), keyword);
+        }
+    }
 }
@@ -0,0 +1,214 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::repl::{Session, PROMPT};
+
+/// One line typed at the REPL prompt and everything it printed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Entry {
+    pub input: String,
+    pub output: String,
+}
+
+/// A recorded REPL session.
+///
+/// The text form is what a terminal shows: each input follows the prompt on
+/// its own line, and the lines up to the next prompt are its output. Lines
+/// before the first prompt are ignored and can hold comments.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Transcript {
+    pub entries: Vec<Entry>,
+}
+
+impl Transcript {
+    pub fn parse(text: &str) -> Self {
+        let mut entries: Vec<Entry> = vec![];
+
+        for line in text.lines() {
+            if let Some(input) = line.strip_prefix(PROMPT) {
+                entries.push(Entry { input: input.to_string(), output: String::new() });
+            } else if let Some(entry) = entries.last_mut() {
+                entry.output.push_str(line);
+                entry.output.push('\n');
+            }
+        }
+
+        Transcript { entries }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        fs::read_to_string(path).map(|text| Self::parse(&text))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.string())
+    }
+
+    pub fn string(&self) -> String {
+        let mut buffer = String::new();
+
+        for entry in &self.entries {
+            buffer.push_str(PROMPT);
+            buffer.push_str(&entry.input);
+            buffer.push('\n');
+            buffer.push_str(&entry.output);
+            if !entry.output.is_empty() && !entry.output.ends_with('\n') {
+                buffer.push('\n');
+            }
+        }
+
+        buffer
+    }
+
+    /// Runs every input in a fresh session and checks it prints the recorded output.
+    pub fn replay(&self) -> Result<(), Mismatch> {
+        let mut session = Session::new(false);
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            let mut out = vec![];
+            // writing to a Vec cannot fail
+            let _ = session.run(&entry.input, &mut out);
+            let actual = String::from_utf8_lossy(&out);
+
+            if normalize(&entry.input, &actual) != normalize(&entry.input, &entry.output) {
+                return Err(Mismatch {
+                    index,
+                    input: entry.input.clone(),
+                    expected: entry.output.clone(),
+                    actual: actual.to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Output as compared by `replay`: trailing newlines are dropped and, for
+/// `:time` inputs, the elapsed time on the last line is masked, since it
+/// differs on every run.
+fn normalize(input: &str, output: &str) -> String {
+    let mut lines: Vec<&str> = output.trim_end_matches('\n').split('\n').collect();
+
+    if input.trim().starts_with(":time") {
+        if let Some(last) = lines.last_mut().filter(|line| line.starts_with("time: ")) {
+            *last = "time: <elapsed>";
+        }
+    }
+    lines.join("\n")
+}
+
+/// The first input of a replayed transcript whose output changed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub index: usize,
+    pub input: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "output of input #{} changed: {}{}", self.index + 1, PROMPT, self.input)?;
+        writeln!(f, "expected:")?;
+        for line in self.expected.lines() {
+            writeln!(f, "  {}", line)?;
+        }
+        writeln!(f, "actual:")?;
+        for line in self.actual.lines() {
+            writeln!(f, "  {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SESSION: &str = "\
+A session recorded with :save
+|monkey> let x = 5;
+Let
+Ident(\"x\")
+Assign
+Int(5)
+SemiColon
+|monkey> :reset
+|monkey> @
+error: illegal character `@`
+ --> <repl>:1:1
+  |
+1 | @
+  | ^ not a valid Monkey token
+
+";
+
+    #[test]
+    fn parse() {
+        let transcript = Transcript::parse(SESSION);
+
+        let inputs: Vec<&str> = transcript.entries.iter().map(|e| e.input.as_str()).collect();
+        assert_eq!(inputs, vec!["let x = 5;", ":reset", "@"]);
+        assert_eq!(transcript.entries[1].output, "");
+        assert!(transcript.entries[2].output.ends_with("token\n\n"));
+
+        let rendered = transcript.string();
+        assert_eq!(rendered, SESSION.trim_start_matches("A session recorded with :save\n"));
+        assert_eq!(Transcript::parse(&rendered), transcript);
+    }
+
+    #[test]
+    fn replay() {
+        let transcript = Transcript::parse(SESSION);
+        assert_eq!(transcript.replay(), Ok(()));
+    }
+
+    #[test]
+    fn replay_time() {
+        let transcript = Transcript::parse("\
+|monkey> :time x
+Ident(\"x\")
+time: 1.234ms
+");
+        assert_eq!(transcript.replay(), Ok(()));
+
+        let mut session = Session::new(false);
+        session.run(":time x", &mut vec![]).unwrap();
+        assert_eq!(session.transcript().replay(), Ok(()));
+
+        // only the last line of a `:time` input is masked
+        assert_eq!(normalize(":time x", "x\ntime: 1ms\n"), normalize(":time x", "x\ntime: 2ms"));
+        assert_ne!(normalize(":time x", "time: 1ms\nx"), normalize(":time x", "time: 2ms\nx"));
+        assert_ne!(normalize("x", "time: 1ms"), normalize("x", "time: 2ms"));
+    }
+
+    #[test]
+    fn replay_mismatch() {
+        let mut transcript = Transcript::parse(SESSION);
+        transcript.entries[0].output = String::from("Let\n");
+
+        let mismatch = transcript.replay().unwrap_err();
+        assert_eq!(mismatch.index, 0);
+        assert_eq!(mismatch.input, "let x = 5;");
+        assert!(mismatch.actual.starts_with("Let\nIdent(\"x\")\n"), "Unexpected output {}", mismatch.actual);
+    }
+
+    #[test]
+    fn record_and_save() {
+        let path = std::env::temp_dir().join(format!("monkey-session-{}.txt", std::process::id()));
+        let mut session = Session::new(false);
+        let mut out = vec![];
+
+        session.run("let x = 5;", &mut out).unwrap();
+        session.run(":reset", &mut out).unwrap();
+        session.run("@", &mut out).unwrap();
+        session.run(&format!(":save {}", path.display()), &mut out).unwrap();
+
+        let saved = Transcript::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(saved, Transcript::parse(SESSION));
+    }
+}